@@ -1,15 +1,37 @@
 use ratatui::widgets::ScrollbarState;
 use serde::Deserialize;
 
-use crate::config::ConfigToml;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{self, BufWriter},
+    path::Path,
+    sync::mpsc::{Receiver, TryRecvError},
+};
 
-#[derive(Clone, Debug)]
-pub struct Package {
-    pub name: String,
-    pub info: Vec<String>,
-}
+use crate::{
+    check::{spawn_check, CheckLevel, PackageCheck},
+    config::ConfigToml,
+    date::parse_iso_date,
+    depends::{broken_dependencies, BrokenDependency},
+    deptree::{dep_tree_lines, TreeDirection},
+    diff::DiffEntry,
+    dot::{write_dot, GraphOptions},
+    export::{export_packages, ExportFormat},
+    filetree::file_tree_lines,
+    history::{read_log, LogEvent},
+    loader::LoadEvent,
+    orphans::orphan_reason,
+    owns::find_owners,
+    package::{dep_name, format_size, format_size_delta, info_line, InfoLink, Package},
+    paclist::{get_package_list, ListOptions, PaclistError},
+    provides::ProvidesIndex,
+    removal::removal_impact,
+    rollback::plan_rollback,
+};
 
-#[derive(Debug, Deserialize, Copy, Clone)]
+#[derive(Debug, Deserialize, Copy, Clone, PartialEq, Eq)]
 pub enum Mode {
     #[serde(alias = "normal", alias = "NORMAL")]
     Normal,
@@ -22,6 +44,60 @@ pub enum Mode {
     Display,
 }
 
+// What the info pane shows for the selected package
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InfoView {
+    Info,
+    Files,
+    History,
+    // Integrity check results, whichever package is selected
+    Check,
+    Deps,
+    ReverseDeps,
+    // What removing the package with pacman -Rs would remove and break
+    Removal,
+}
+
+// Order of the package list
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    Version,
+    Size,
+    InstallDate,
+}
+impl SortKey {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "name" => Some(SortKey::Name),
+            "version" => Some(SortKey::Version),
+            "size" => Some(SortKey::Size),
+            "date" => Some(SortKey::InstallDate),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::Version => "version",
+            SortKey::Size => "size",
+            SortKey::InstallDate => "date",
+        }
+    }
+
+    // Packages that compare equal are ordered by name.
+    pub fn compare(&self, a: &Package, b: &Package) -> Ordering {
+        match self {
+            SortKey::Name => Ordering::Equal,
+            SortKey::Version => a.cmp_version(b),
+            SortKey::Size => a.installed_size.cmp(&b.installed_size),
+            SortKey::InstallDate => a.install_date.cmp(&b.install_date),
+        }
+        .then_with(|| a.name.cmp(&b.name))
+    }
+}
+
 pub enum Location {
     Search,
     Paclist,
@@ -34,11 +110,58 @@ pub struct App {
     pub config: ConfigToml,
     packages: Vec<Package>,
     displayed_packages_indices: Vec<usize>,
+    show_installed_marker: bool,
+    loader: Option<Receiver<LoadEvent>>,
+    // (loaded, total) while the package list is still loading
+    load_progress: Option<(usize, usize)>,
+    pub load_error: Option<PaclistError>,
+    pub load_warnings: Vec<String>,
+    // Source of the packages when browsing a snapshot rather than the live system
+    pub snapshot: Option<String>,
+    // Differences from the loader in diff mode, in the same order as `packages`
+    diff: Option<Vec<DiffEntry>>,
+    // Names of the (old, new) package sets being compared
+    pub diff_sources: Option<(String, String)>,
+    // Whether the list is of packages with upgrades available
+    pub upgrades: bool,
+    // Whether the list is of orphaned dependencies
+    pub orphans: bool,
+    // Whether the list is of packages with broken dependencies, and what is broken for each
+    pub broken: bool,
+    broken_deps: HashMap<String, Vec<BrokenDependency>>,
+    sort_key: SortKey,
+    sort_descending: bool,
     pub current_search: String,
     pub current_command: String,
     pub current_paclist: Vec<String>,
     pub current_pacinfo: Vec<String>,
-    display_text: &'static str,
+    pub info_view: InfoView,
+    folded_dirs: HashSet<String>,
+    file_filter: String,
+    // Full path of each line in the files view
+    current_file_paths: Vec<String>,
+    // Packages named in each line of the info view, and which one on the current line is selected
+    current_info_links: Vec<Vec<InfoLink>>,
+    info_link_index: usize,
+    // Packages to return to with Ctrl-O, most recent last, and to go forward to again with Ctrl-I
+    jump_back: Vec<String>,
+    jump_forward: Vec<String>,
+    // Expanded nodes of each dependency tree
+    expanded_deps: HashMap<TreeDirection, HashSet<String>>,
+    // Node of each expandable line in the dependency tree views
+    current_dep_nodes: Vec<Option<String>>,
+    // Every installed package, or every package in the snapshot, whatever the list shows. A
+    // snapshot is kept from when the list loaded; installed packages are read the first time a
+    // dependency view needs them.
+    all_packages: Option<Result<Vec<Package>, String>>,
+    // pacman.log events, read the first time the history view is opened
+    history: Option<Result<Vec<LogEvent>, String>>,
+    checker: Option<Receiver<PackageCheck>>,
+    check_level: CheckLevel,
+    // Number of packages in the current integrity check
+    check_total: usize,
+    check_results: Vec<PackageCheck>,
+    display_text: String,
     pub list_scroll_state: ScrollbarState,
     pub info_scroll_state: ScrollbarState,
     pub search_cursor_index: usize,
@@ -47,16 +170,46 @@ pub struct App {
     pub command_cursor_index: usize,
 }
 impl App {
-    pub fn new(config: ConfigToml, packages: Vec<Package>) -> Self {
+    pub fn new(config: ConfigToml, loader: Receiver<LoadEvent>) -> Self {
         let mut app = Self {
             mode: config.operation.starting_mode,
-            displayed_packages_indices: (0..packages.len()).collect(),
-            packages,
+            packages: Vec::new(),
+            displayed_packages_indices: Vec::new(),
+            show_installed_marker: false,
+            loader: Some(loader),
+            load_progress: Some((0, 0)),
+            load_error: None,
+            load_warnings: Vec::new(),
+            snapshot: None,
+            diff: None,
+            diff_sources: None,
+            upgrades: false,
+            orphans: false,
+            broken: false,
+            broken_deps: HashMap::new(),
+            sort_key: SortKey::Name,
+            sort_descending: false,
             current_search: String::new(),
             current_command: String::new(),
             current_paclist: vec![String::from("")],
             current_pacinfo: vec![String::from("")],
-            display_text: "",
+            info_view: InfoView::Info,
+            folded_dirs: HashSet::new(),
+            file_filter: String::new(),
+            current_file_paths: Vec::new(),
+            current_info_links: Vec::new(),
+            info_link_index: 0,
+            jump_back: Vec::new(),
+            jump_forward: Vec::new(),
+            expanded_deps: HashMap::new(),
+            current_dep_nodes: Vec::new(),
+            all_packages: None,
+            history: None,
+            checker: None,
+            check_level: CheckLevel::Files,
+            check_total: 0,
+            check_results: Vec::new(),
+            display_text: String::new(),
             list_scroll_state: ScrollbarState::default(),
             info_scroll_state: ScrollbarState::default(),
             search_cursor_index: 0,
@@ -69,8 +222,264 @@ impl App {
         app
     }
 
+    // Apply any pending events from the loader. Returns true if the app should exit, i.e. loading
+    // failed or finished without finding any packages.
+    pub fn poll_loader(&mut self) -> bool {
+        let Some(loader) = &self.loader else {
+            return false;
+        };
+        let mut new_packages: Vec<Package> = Vec::new();
+        let mut finished: Option<Vec<Package>> = None;
+        loop {
+            match loader.try_recv() {
+                Ok(LoadEvent::Progress { loaded, total }) => {
+                    self.load_progress = Some((loaded, total));
+                }
+                Ok(LoadEvent::Packages(packages)) => new_packages.extend(packages),
+                Ok(LoadEvent::Finished(packages)) => {
+                    finished = Some(packages);
+                    break;
+                }
+                // Kept for the dependency views, since the snapshot may not be readable again
+                Ok(LoadEvent::Snapshot(packages)) => self.all_packages = Some(Ok(packages)),
+                Ok(LoadEvent::Diff(entries)) => {
+                    finished = Some(
+                        entries
+                            .iter()
+                            .map(|entry| entry.package().clone())
+                            .collect(),
+                    );
+                    self.diff = Some(entries);
+                    break;
+                }
+                Ok(LoadEvent::Warning(warning)) => self.load_warnings.push(warning),
+                Ok(LoadEvent::Failed(e)) => {
+                    self.load_error = Some(e);
+                    self.loader = None;
+                    return true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    finished = Some(std::mem::take(&mut self.packages));
+                    break;
+                }
+            }
+        }
+
+        if let Some(packages) = finished {
+            self.loader = None;
+            self.load_progress = None;
+            self.set_packages(packages);
+            if !self.load_warnings.is_empty() {
+                self.goto_display_mode(&self.load_warnings.join("\n"));
+            }
+            return self.packages.is_empty();
+        }
+        if !new_packages.is_empty() {
+            self.packages.extend(new_packages);
+            self.packages.sort_by(|a, b| a.name.cmp(&b.name));
+            self.refilter();
+        }
+        false
+    }
+
+    pub fn set_packages(&mut self, packages: Vec<Package>) {
+        self.show_installed_marker = packages.iter().any(|package| !package.installed);
+        self.packages = packages;
+        if self.broken {
+            self.find_broken_deps();
+        }
+        self.refilter();
+    }
+
+    // Dependencies are checked against every installed package, not just the listed ones.
+    fn find_broken_deps(&mut self) {
+        self.load_all_packages();
+        if let Some(Ok(all_packages)) = &self.all_packages {
+            let provides_index = ProvidesIndex::new(all_packages);
+            self.broken_deps = self
+                .packages
+                .iter()
+                .map(|package| {
+                    (
+                        package.name.to_owned(),
+                        broken_dependencies(package, &provides_index),
+                    )
+                })
+                .collect();
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.loader.is_some()
+    }
+
+    pub fn package_count(&self) -> usize {
+        self.packages.len()
+    }
+
+    pub fn list_title(&self) -> String {
+        let position = if self.displayed_packages_indices.is_empty() {
+            0
+        } else {
+            self.list_cursor_index + 1
+        };
+        let source_name = |source: &str| {
+            if source == "-" {
+                String::from("<stdin>")
+            } else {
+                source.to_owned()
+            }
+        };
+        let mut title = match (&self.diff_sources, &self.snapshot) {
+            (Some((old, new)), _) => format!(
+                "DIFF {} -> {} - Packages",
+                source_name(old),
+                source_name(new)
+            ),
+            (None, Some(snapshot)) => format!("SNAPSHOT {} - Packages", source_name(snapshot)),
+            (None, None) if self.upgrades => String::from("Upgrades"),
+            (None, None) if self.orphans => String::from("Orphans"),
+            (None, None) if self.broken => String::from("Broken dependencies"),
+            (None, None) => String::from("Packages"),
+        };
+        title.push_str(&format!(
+            " ({}/{})",
+            position,
+            self.displayed_packages_indices.len()
+        ));
+        if self.sort_key != SortKey::Name || self.sort_descending {
+            title.push_str(&format!(
+                " by {}{}",
+                self.sort_key.name(),
+                if self.sort_descending {
+                    ", descending"
+                } else {
+                    ""
+                }
+            ));
+        }
+        if self.upgrades && self.load_progress.is_none() {
+            let (download_size, size_delta) = self.upgrade_totals();
+            title.push_str(&format!(
+                " {} download, {} installed",
+                format_size(download_size),
+                format_size_delta(size_delta)
+            ));
+        }
+        if self.orphans && self.load_progress.is_none() {
+            let reclaimable = self
+                .displayed_packages()
+                .iter()
+                .map(|package| package.installed_size)
+                .sum();
+            title.push_str(&format!(" {} reclaimable", format_size(reclaimable)));
+        }
+        if self.broken && self.load_progress.is_none() {
+            let unsatisfied = self
+                .displayed_packages()
+                .iter()
+                .filter_map(|package| self.broken_deps.get(&package.name))
+                .map(Vec::len)
+                .sum::<usize>();
+            title.push_str(&format!(" {} unsatisfied", unsatisfied));
+        }
+        if let Some((loaded, total)) = self.load_progress {
+            title.push_str(&format!(" loading {}/{}", loaded, total));
+        }
+        title
+    }
+
+    // Total download size and change in installed size of the displayed upgrades
+    fn upgrade_totals(&self) -> (u64, i64) {
+        self.displayed_packages()
+            .iter()
+            .fold((0, 0), |(download_size, size_delta), package| {
+                (
+                    download_size + package.download_size.unwrap_or(0),
+                    size_delta
+                        + package
+                            .repo_installed_size
+                            .map_or(0, |repo_installed_size| {
+                                repo_installed_size as i64 - package.installed_size as i64
+                            }),
+                )
+            })
+    }
+
     pub fn print_package_list(&self) {
-        println!("{}", self.current_paclist.join("\n"));
+        println!("{}", self.displayed_package_names().join("\n"));
+    }
+
+    pub fn displayed_packages(&self) -> Vec<&Package> {
+        self.displayed_packages_indices
+            .iter()
+            .map(|index| &self.packages[*index])
+            .collect()
+    }
+
+    // Export the current package list, choosing the format from the file extension.
+    pub fn export_package_list(&self, path: &Path) -> io::Result<usize> {
+        let format = ExportFormat::from_path(path).ok_or_else(|| {
+            io::Error::other("Unknown export format. Use a .json, .csv or .toml file.")
+        })?;
+        let packages = self.displayed_packages();
+        export_packages(&packages, format, BufWriter::new(File::create(path)?))?;
+        Ok(packages.len())
+    }
+
+    // Write the dependency graph of the selected package, or of every listed package, as DOT.
+    pub fn export_dot(
+        &mut self,
+        path: &Path,
+        all: bool,
+        options: &GraphOptions,
+    ) -> io::Result<usize> {
+        self.load_all_packages();
+        let roots = if all {
+            self.displayed_packages()
+        } else {
+            self.selected_package().into_iter().collect()
+        };
+        match &self.all_packages {
+            Some(Ok(packages)) => write_dot(
+                &roots,
+                packages,
+                options,
+                BufWriter::new(File::create(path)?),
+            ),
+            Some(Err(e)) => Err(io::Error::other(format!("Unable to read packages: {}", e))),
+            None => Ok(0),
+        }
+    }
+
+    pub fn displayed_package_names(&self) -> Vec<String> {
+        self.displayed_packages_indices
+            .iter()
+            .map(|index| self.packages[*index].name.to_owned())
+            .collect()
+    }
+
+    // When uninstalled packages are listed, mark the installed ones like pacman -Ss does. When
+    // comparing package sets, mark how each package changed instead, and when listing upgrades,
+    // show the versions like pacman -Qu does.
+    fn list_label(&self, index: usize) -> String {
+        let package = &self.packages[index];
+        if let Some(diff) = &self.diff {
+            format!("{} {}", diff[index].change.marker(), package.name)
+        } else if let (true, Some(upgrade)) = (self.upgrades, package.upgrade()) {
+            format!(
+                "{} {} -> {} [{}]",
+                package.name,
+                package.version,
+                package.repo_version.as_deref().unwrap_or_default(),
+                upgrade.name()
+            )
+        } else if self.show_installed_marker && package.installed {
+            format!("{} [installed]", package.name)
+        } else {
+            package.name.to_owned()
+        }
     }
 
     pub fn refresh_current_paclist(&mut self) {
@@ -82,7 +491,7 @@ impl App {
                 if self.selected_package().is_some() {
                     self.displayed_packages_indices
                         .iter()
-                        .map(|index| self.packages[*index].name.to_owned())
+                        .map(|index| self.list_label(*index))
                         .collect::<Vec<String>>()
                 } else {
                     vec![String::from("")]
@@ -95,25 +504,551 @@ impl App {
     }
 
     pub fn refresh_current_pacinfo(&mut self) {
+        self.current_file_paths = Vec::new();
+        self.current_dep_nodes = Vec::new();
+        self.current_info_links = Vec::new();
+        self.info_link_index = 0;
         self.current_pacinfo = match self.mode {
             Mode::Display => self
                 .display_text
                 .lines()
                 .map(|line| line.to_owned())
                 .collect::<Vec<String>>(),
-            _ => {
-                if let Some(selected_package) = self.selected_package() {
-                    selected_package.info.to_owned()
-                } else {
-                    vec![String::from("")]
+            _ => match (self.selected_package(), self.info_view) {
+                (Some(selected_package), InfoView::Info) => match self.selected_diff_entry() {
+                    Some(entry) => entry.info_lines(),
+                    None => {
+                        let (mut lines, mut links) = selected_package.linked_info_lines();
+                        if self.orphans {
+                            lines
+                                .insert(0, info_line("Orphaned", &orphan_reason(selected_package)));
+                            links.insert(0, Vec::new());
+                        }
+                        // List what is broken first, linking each dependency
+                        if let Some(broken_deps) = self.broken_deps.get(&selected_package.name) {
+                            for (index, broken_dep) in broken_deps.iter().enumerate().rev() {
+                                let description = broken_dep.description();
+                                let line = info_line(
+                                    if index == 0 { "Broken Deps" } else { "" },
+                                    &description,
+                                );
+                                let start = line.len() - description.len();
+                                links.insert(
+                                    0,
+                                    vec![InfoLink {
+                                        start,
+                                        end: start + broken_dep.dependency.len(),
+                                        target: dep_name(&broken_dep.dependency).to_owned(),
+                                    }],
+                                );
+                                lines.insert(0, line);
+                            }
+                        }
+                        self.current_info_links = links;
+                        lines
+                    }
+                },
+                (Some(selected_package), InfoView::Files) => {
+                    let tree = file_tree_lines(
+                        &selected_package.files,
+                        &self.folded_dirs,
+                        &self.file_filter,
+                    );
+                    if tree.is_empty() {
+                        vec![String::from("No files")]
+                    } else {
+                        let (lines, paths) =
+                            tree.into_iter().map(|line| (line.text, line.path)).unzip();
+                        self.current_file_paths = paths;
+                        lines
+                    }
                 }
-            }
+                (Some(selected_package), InfoView::Deps | InfoView::ReverseDeps) => {
+                    let direction = self.tree_direction().unwrap();
+                    match &self.all_packages {
+                        Some(Ok(packages)) => {
+                            let (lines, nodes) = dep_tree_lines(
+                                selected_package,
+                                packages,
+                                direction,
+                                self.expanded_deps
+                                    .get(&direction)
+                                    .unwrap_or(&HashSet::new()),
+                            )
+                            .into_iter()
+                            .map(|line| (line.text, line.expandable.then_some(line.node)))
+                            .unzip();
+                            self.current_dep_nodes = nodes;
+                            lines
+                        }
+                        Some(Err(e)) => vec![format!("Unable to read packages: {}", e)],
+                        None => vec![String::from("")],
+                    }
+                }
+                (Some(selected_package), InfoView::Removal) => match &self.all_packages {
+                    Some(Ok(packages)) => {
+                        match packages
+                            .iter()
+                            .find(|package| package.name == selected_package.name)
+                        {
+                            Some(installed) => removal_impact(installed, packages).lines(),
+                            None => vec![format!("{} is not installed", selected_package.name)],
+                        }
+                    }
+                    Some(Err(e)) => vec![format!("Unable to read packages: {}", e)],
+                    None => vec![String::from("")],
+                },
+                (_, InfoView::Check) => self.check_lines(),
+                (Some(selected_package), InfoView::History) => {
+                    self.history_lines(&selected_package.name)
+                }
+                (None, _) => vec![String::from("")],
+            },
         };
         self.info_scroll_state = self
             .info_scroll_state
             .content_length(self.current_pacinfo.len());
     }
 
+    pub fn info_title(&self) -> String {
+        match (self.mode, self.info_view, self.selected_package()) {
+            (Mode::Display, _, _) => String::from(""),
+            (_, InfoView::Check, _) => format!(
+                "Integrity check ({}) {}/{} packages, {} with problems{}",
+                self.check_level.flag(),
+                self.check_results.len(),
+                self.check_total,
+                self.check_results
+                    .iter()
+                    .filter(|check| check.has_problems())
+                    .count(),
+                if self.checker.is_some() { " ..." } else { "" }
+            ),
+            (_, _, None) => String::from(""),
+            (_, InfoView::Info, Some(pkg)) => match self.selected_diff_entry() {
+                Some(entry) => format!("{} {}", pkg.name, entry.summary()),
+                None => pkg.name.to_owned(),
+            },
+            (_, InfoView::Files, Some(pkg)) if self.file_filter.is_empty() => {
+                format!("{} files ({})", pkg.name, pkg.files.len())
+            }
+            (_, InfoView::Files, Some(pkg)) => {
+                format!("{} files matching \"{}\"", pkg.name, self.file_filter)
+            }
+            (_, InfoView::History, Some(pkg)) => format!("{} history", pkg.name),
+            (_, InfoView::Deps, Some(pkg)) => format!("{} dependency tree", pkg.name),
+            (_, InfoView::ReverseDeps, Some(pkg)) => {
+                format!("{} reverse dependency tree", pkg.name)
+            }
+            (_, InfoView::Removal, Some(pkg)) => format!("{} removal impact", pkg.name),
+        }
+    }
+
+    // Text to copy for the current info line; in the files view this is the full path.
+    pub fn selected_info_line(&self) -> String {
+        match self.current_file_paths.get(self.info_cursor_index) {
+            Some(path) => path.to_owned(),
+            None => self.current_pacinfo[self.info_cursor_index].to_owned(),
+        }
+    }
+
+    // Switch the info pane to the given view, or back to package info if it is already shown.
+    pub fn toggle_info_view(&mut self, view: InfoView) {
+        self.info_view = if self.info_view == view {
+            InfoView::Info
+        } else {
+            view
+        };
+        if self.info_view == InfoView::History && self.history.is_none() {
+            self.history = Some(read_log(&self.config.paths.logfile()).map_err(|e| e.to_string()));
+        }
+        if self.tree_direction().is_some() || self.info_view == InfoView::Removal {
+            self.load_all_packages();
+        }
+        self.refresh_current_pacinfo();
+        self.reset_info_scroll();
+    }
+
+    // Check the selected package's files, or those of every displayed package if `all` is set.
+    pub fn start_check(&mut self, level: CheckLevel, all: bool) {
+        let packages = if all {
+            self.displayed_packages()
+                .into_iter()
+                .cloned()
+                .collect::<Vec<Package>>()
+        } else {
+            self.selected_package().into_iter().cloned().collect()
+        };
+        self.check_level = level;
+        self.check_total = packages.len();
+        self.check_results = Vec::new();
+        self.checker = Some(spawn_check(packages, self.config.paths.clone(), level));
+        self.info_view = InfoView::Check;
+        self.refresh_current_pacinfo();
+        self.reset_info_scroll();
+    }
+
+    // Collect any new integrity check results.
+    pub fn poll_checker(&mut self) {
+        let Some(checker) = &self.checker else {
+            return;
+        };
+        let mut changed = false;
+        loop {
+            match checker.try_recv() {
+                Ok(check) => {
+                    self.check_results.push(check);
+                    changed = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.checker = None;
+                    changed = true;
+                    break;
+                }
+            }
+        }
+        if changed && self.info_view == InfoView::Check && self.mode != Mode::Display {
+            self.refresh_current_pacinfo();
+        }
+    }
+
+    fn check_lines(&self) -> Vec<String> {
+        if self.check_results.is_empty() {
+            return vec![String::from(if self.checker.is_some() {
+                "Checking..."
+            } else {
+                "No integrity check has been run. Use :qk or :qkk."
+            })];
+        }
+        self.check_results
+            .iter()
+            .flat_map(|check| check.lines())
+            .collect()
+    }
+
+    // Timeline of the package's changes in pacman.log, oldest first.
+    fn history_lines(&self, name: &str) -> Vec<String> {
+        match &self.history {
+            Some(Ok(events)) => {
+                let lines = events
+                    .iter()
+                    .filter(|event| event.name == name)
+                    .map(|event| event.timeline_line())
+                    .collect::<Vec<String>>();
+                if lines.is_empty() {
+                    vec![format!(
+                        "No history for {} in {}",
+                        name,
+                        self.config.paths.logfile().display()
+                    )]
+                } else {
+                    lines
+                }
+            }
+            Some(Err(e)) => vec![format!("Unable to read pacman log: {}", e)],
+            None => vec![String::from("")],
+        }
+    }
+
+    fn load_all_packages(&mut self) {
+        if self.all_packages.is_none() {
+            let options = ListOptions {
+                input: self.snapshot.clone(),
+                ..ListOptions::default()
+            };
+            self.all_packages = Some(
+                // Any warnings were already shown when the list loaded
+                get_package_list(
+                    &options,
+                    &self.config.paths,
+                    &mut |_, _, _| {},
+                    &mut Vec::new(),
+                )
+                .map_err(|e| e.to_string()),
+            );
+        }
+    }
+
+    pub fn set_file_filter(&mut self, filter: &str) {
+        self.info_view = InfoView::Files;
+        self.file_filter = filter.to_owned();
+        self.refresh_current_pacinfo();
+        self.reset_info_scroll();
+    }
+
+    // Fold or unfold the directory under the info cursor.
+    pub fn toggle_fold(&mut self) {
+        if let Some(path) = self.current_file_paths.get(self.info_cursor_index) {
+            if path.ends_with('/') && !self.folded_dirs.remove(path) {
+                self.folded_dirs.insert(path.to_owned());
+            }
+            self.refresh_current_pacinfo();
+        }
+    }
+
+    pub fn fold_all(&mut self) {
+        if let Some(selected_package) = self.selected_package() {
+            let dirs = selected_package
+                .files
+                .iter()
+                .filter(|path| path.ends_with('/'))
+                .cloned()
+                .collect::<Vec<String>>();
+            self.folded_dirs.extend(dirs);
+            self.refresh_current_pacinfo();
+            self.reset_info_scroll();
+        }
+    }
+
+    pub fn unfold_all(&mut self) {
+        self.folded_dirs.clear();
+        self.refresh_current_pacinfo();
+    }
+
+    // Direction of the dependency tree in the info pane, if one is shown
+    pub fn tree_direction(&self) -> Option<TreeDirection> {
+        match self.info_view {
+            InfoView::Deps => Some(TreeDirection::Depends),
+            InfoView::ReverseDeps => Some(TreeDirection::RequiredBy),
+            _ => None,
+        }
+    }
+
+    // Expand or collapse the dependency under the info cursor.
+    pub fn toggle_dep_expansion(&mut self) {
+        let Some(direction) = self.tree_direction() else {
+            return;
+        };
+        if let Some(Some(node)) = self.current_dep_nodes.get(self.info_cursor_index) {
+            let expanded = self.expanded_deps.entry(direction).or_default();
+            if !expanded.remove(node) {
+                expanded.insert(node.to_owned());
+            }
+            self.refresh_current_pacinfo();
+        }
+    }
+
+    // Expand every dependency that can be, which lists each package reachable from the selected
+    // one exactly once.
+    pub fn expand_all_deps(&mut self) {
+        let Some(direction) = self.tree_direction() else {
+            return;
+        };
+        loop {
+            let expanded = self.expanded_deps.entry(direction).or_default();
+            let nodes = self
+                .current_dep_nodes
+                .iter()
+                .flatten()
+                .filter(|node| !expanded.contains(*node))
+                .cloned()
+                .collect::<Vec<String>>();
+            if nodes.is_empty() {
+                break;
+            }
+            expanded.extend(nodes);
+            self.refresh_current_pacinfo();
+        }
+    }
+
+    pub fn collapse_all_deps(&mut self) {
+        if let Some(direction) = self.tree_direction() {
+            self.expanded_deps.remove(&direction);
+            self.refresh_current_pacinfo();
+            // The cursor may have been on a line that's now collapsed away
+            self.info_cursor_index = self
+                .info_cursor_index
+                .min(self.current_pacinfo.len().saturating_sub(1));
+            self.update_scroll_state(&Location::Pacinfo);
+        }
+    }
+
+    // The package name selected on the current info line, if it names any.
+    pub fn selected_info_link(&self) -> Option<&InfoLink> {
+        let links = self.current_info_links.get(self.info_cursor_index)?;
+        links.get(self.info_link_index.min(links.len().checked_sub(1)?))
+    }
+
+    // Move between the package names on the current info line, wrapping around.
+    pub fn next_info_link(&mut self, forward: bool) {
+        let count = self
+            .current_info_links
+            .get(self.info_cursor_index)
+            .map_or(0, Vec::len);
+        if count > 0 {
+            let index = self.info_link_index.min(count - 1);
+            self.info_link_index = if forward {
+                (index + 1) % count
+            } else {
+                (index + count - 1) % count
+            };
+        }
+    }
+
+    // Jump to the package named under the info cursor, remembering where we came from. Virtual
+    // packages jump to their provider, or list every provider if there are several.
+    pub fn follow_info_link(&mut self) {
+        let Some(target) = self.selected_info_link().map(|link| link.target.to_owned()) else {
+            return;
+        };
+        let Some(current) = self
+            .selected_package()
+            .map(|package| package.name.to_owned())
+        else {
+            return;
+        };
+        if self.select_package(&target) {
+            self.record_jump(current);
+            return;
+        }
+        let providers = match self.providers(&target) {
+            Ok(providers) => providers,
+            Err(e) => return self.goto_display_mode(&e),
+        };
+        match providers.as_slice() {
+            [] => self.goto_display_mode(&format!("Nothing installed provides {}", target)),
+            // Following a package's own provision lists the other providers, if any
+            [(provider, _)] if *provider == current => self.show_providers(&target),
+            [(provider, _)] if self.select_package(provider) => self.record_jump(current),
+            [(provider, _)] if *provider == target => {
+                self.goto_display_mode(&format!("{} is not in the package list", target))
+            }
+            [(provider, _)] => self.goto_display_mode(&format!(
+                "{} is provided by {}, which is not in the package list",
+                target, provider
+            )),
+            _ => self.show_providers(&target),
+        }
+    }
+
+    fn record_jump(&mut self, from: String) {
+        self.jump_back.push(from);
+        self.jump_forward.clear();
+    }
+
+    // Names and versions of the installed packages satisfying `name`, which may be virtual.
+    fn providers(&mut self, name: &str) -> Result<Vec<(String, String)>, String> {
+        self.load_all_packages();
+        match &self.all_packages {
+            Some(Ok(packages)) => Ok(ProvidesIndex::new(packages)
+                .satisfiers(name)
+                .into_iter()
+                .map(|package| (package.name.to_owned(), package.version.to_owned()))
+                .collect()),
+            Some(Err(e)) => Err(format!("Unable to read packages: {}", e)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    // List every installed package satisfying `name`, such as the providers of a virtual package.
+    pub fn show_providers(&mut self, name: &str) {
+        let text = match self.providers(name) {
+            Ok(providers) if providers.is_empty() => {
+                format!("Nothing installed provides {}", name)
+            }
+            Ok(providers) => {
+                let mut lines = vec![format!("{} is provided by:", name)];
+                lines.extend(
+                    providers
+                        .iter()
+                        .map(|(name, version)| format!("  {} {}", name, version)),
+                );
+                lines.join("\n")
+            }
+            Err(e) => e,
+        };
+        self.goto_display_mode(&text);
+    }
+
+    // Go back to the package visited before the last jump, or forward again, skipping any that
+    // are no longer listed.
+    pub fn jump(&mut self, back: bool) {
+        let Some(current) = self
+            .selected_package()
+            .map(|package| package.name.to_owned())
+        else {
+            return;
+        };
+        loop {
+            let name = if back {
+                self.jump_back.pop()
+            } else {
+                self.jump_forward.pop()
+            };
+            let Some(name) = name else {
+                return;
+            };
+            if name != current && self.select_package(&name) {
+                if back {
+                    self.jump_forward.push(current);
+                } else {
+                    self.jump_back.push(current);
+                }
+                return;
+            }
+        }
+    }
+
+    // Move the list cursor to the named package, clearing the search if it hides the package.
+    pub fn select_package(&mut self, name: &str) -> bool {
+        let position = |app: &Self| {
+            app.displayed_packages_indices
+                .iter()
+                .position(|index| app.packages[*index].name == name)
+        };
+        if position(self).is_none() && !self.current_search.is_empty() {
+            self.clear(Location::Search);
+        }
+        match position(self) {
+            Some(position) => {
+                self.cursor_jump(&Location::Paclist, position);
+                self.reset_info_scroll();
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Select the package owning `path` and show the file in its files view. Owners are looked up
+    // among every installed package, whatever is listed.
+    pub fn show_owner(&mut self, path: &str) {
+        self.load_all_packages();
+        let owners = match &self.all_packages {
+            Some(Ok(packages)) => {
+                find_owners(packages, path, &self.config.paths.root).map(|(file, owners)| {
+                    let names = owners
+                        .iter()
+                        .map(|package| package.name.to_owned())
+                        .collect::<Vec<String>>();
+                    (file, names)
+                })
+            }
+            Some(Err(e)) => {
+                self.goto_display_mode(&format!("Unable to read packages: {}", e));
+                return;
+            }
+            None => None,
+        };
+        match owners {
+            None => self.goto_display_mode(&format!("No package owns {}", path)),
+            Some((file, names)) => {
+                let selected = self.select_package(&names[0]);
+                if names.len() > 1 {
+                    self.goto_display_mode(&format!("{} is owned by {}", file, names.join("  ")));
+                } else if selected {
+                    self.set_file_filter(&file);
+                    self.mode = Mode::Info;
+                } else {
+                    self.goto_display_mode(&format!(
+                        "{} is owned by {}, which is not in the package list",
+                        file, names[0]
+                    ));
+                }
+            }
+        }
+    }
+
     pub fn selected_package(&self) -> Option<&Package> {
         if !self.displayed_packages_indices.is_empty() {
             Some(&self.packages[self.displayed_packages_indices[self.list_cursor_index]])
@@ -122,14 +1057,65 @@ impl App {
         }
     }
 
+    fn selected_diff_entry(&self) -> Option<&DiffEntry> {
+        let diff = self.diff.as_ref()?;
+        let index = self
+            .displayed_packages_indices
+            .get(self.list_cursor_index)?;
+        Some(&diff[*index])
+    }
+
     pub fn refresh_search(&mut self) {
         self.cursor_jump(&Location::Paclist, 0);
         self.cursor_jump(&Location::Pacinfo, 0);
-        self.displayed_packages_indices = (0..self.packages.len())
+        self.displayed_packages_indices = self.search_matches();
+        self.refresh_current_paclist();
+        self.refresh_current_pacinfo();
+    }
+
+    fn search_matches(&self) -> Vec<usize> {
+        let mut matches = (0..self.packages.len())
             .filter(|index| self.packages[*index].name.contains(&self.current_search))
-            .collect();
+            .collect::<Vec<usize>>();
+        matches.sort_by(|a, b| {
+            let ordering = self
+                .sort_key
+                .compare(&self.packages[*a], &self.packages[*b]);
+            if self.sort_descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+        matches
+    }
+
+    pub fn sort_packages(&mut self, sort_key: SortKey, descending: bool) {
+        self.sort_key = sort_key;
+        self.sort_descending = descending;
+        self.refilter();
+    }
+
+    // Re-apply the search after the package list changes, keeping the selected package selected.
+    fn refilter(&mut self) {
+        let selected_name = self
+            .selected_package()
+            .map(|package| package.name.to_owned());
+        self.displayed_packages_indices = self.search_matches();
+        self.list_cursor_index = selected_name
+            .and_then(|name| {
+                self.displayed_packages_indices
+                    .iter()
+                    .position(|index| self.packages[*index].name == name)
+            })
+            .unwrap_or(0);
         self.refresh_current_paclist();
         self.refresh_current_pacinfo();
+        self.info_cursor_index = self
+            .info_cursor_index
+            .min(self.current_pacinfo.len().saturating_sub(1));
+        self.update_scroll_state(&Location::Paclist);
+        self.update_scroll_state(&Location::Pacinfo);
     }
 
     fn cursor_change(&mut self, location: &Location, change: i32) -> usize {
@@ -157,6 +1143,7 @@ impl App {
                     if info_len > 0 { info_len - 1 } else { 0 },
                 );
                 self.info_cursor_index = new_index;
+                self.info_link_index = 0;
             }
             Location::Command => {
                 new_index = get_new_index(self.command_cursor_index, self.current_command.len());
@@ -288,14 +1275,46 @@ impl App {
         self.add_char(':', &Location::Command);
     }
 
-    pub fn goto_display_mode(&mut self, string: &'static str) {
+    pub fn goto_display_mode(&mut self, string: &str) {
         self.mode = Mode::Display;
-        self.display_text = string;
+        self.display_text = string.to_owned();
         self.refresh_current_paclist();
         self.refresh_current_pacinfo();
         self.reset_info_scroll();
     }
 
+    // Open the time machine view for the given date, also writing the rollback plan to
+    // `plan_path` if given.
+    pub fn show_rollback(&mut self, date: &str, plan_path: Option<&Path>) {
+        let text = match parse_iso_date(date) {
+            None => format!(
+                "Invalid date \"{}\". Use YYYY-MM-DD or YYYY-MM-DDTHH:MM (UTC).",
+                date
+            ),
+            Some(timestamp) => match plan_rollback(&self.config.paths, timestamp) {
+                Err(e) => format!("Unable to plan rollback: {}", e),
+                Ok(plan) => {
+                    let mut lines = plan.view_lines();
+                    let status = match plan_path {
+                        Some(path) => match plan.write_script(path) {
+                            Ok(()) => format!(
+                                "Wrote the pacman -U plan to {}. Review it before running it.",
+                                path.display()
+                            ),
+                            Err(e) => format!("Unable to write plan to {}: {}", path.display(), e),
+                        },
+                        None => {
+                            format!("Save this as a pacman -U plan with :rollback {} FILE", date)
+                        }
+                    };
+                    lines.insert(2, status);
+                    lines.join("\n")
+                }
+            },
+        };
+        self.goto_display_mode(&text);
+    }
+
     pub fn leave_display_mode(&mut self, new_mode: Mode) {
         self.mode = new_mode;
         self.refresh_current_paclist();
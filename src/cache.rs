@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::{self, BufReader, BufWriter},
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    process,
+    time::UNIX_EPOCH,
+};
+
+use crate::package::Package;
+
+// Bump whenever Package changes shape so stale caches are discarded.
+const CACHE_VERSION: u32 = 2;
+
+// Modification times of a package's desc and files entries, as (seconds, nanoseconds).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryStamp {
+    desc: (u64, u32),
+    files: Option<(u64, u32)>,
+}
+impl EntryStamp {
+    pub fn read(dir: &Path) -> io::Result<Self> {
+        let mtime = |path: PathBuf| -> io::Result<(u64, u32)> {
+            let since_epoch = fs::metadata(path)?
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            Ok((since_epoch.as_secs(), since_epoch.subsec_nanos()))
+        };
+        Ok(Self {
+            desc: mtime(dir.join("desc"))?,
+            files: mtime(dir.join("files")).ok(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    stamp: EntryStamp,
+    package: Package,
+}
+
+// Parsed local database entries, keyed by their directory name under <dbpath>/local.
+#[derive(Serialize, Deserialize)]
+pub struct PackageCache {
+    version: u32,
+    entries: HashMap<String, CacheEntry>,
+}
+impl PackageCache {
+    pub fn new() -> Self {
+        Self {
+            version: CACHE_VERSION,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    // Load the cache saved at `path`. A missing or unreadable cache is simply empty.
+    pub fn load(path: &Path) -> Self {
+        let cache = fs::File::open(path)
+            .ok()
+            .and_then(|file| serde_json::from_reader::<_, Self>(BufReader::new(file)).ok());
+        match cache {
+            Some(cache) if cache.version == CACHE_VERSION => cache,
+            _ => Self::new(),
+        }
+    }
+
+    pub fn get(&self, dir_name: &str, stamp: &EntryStamp) -> Option<&Package> {
+        self.entries
+            .get(dir_name)
+            .filter(|entry| entry.stamp == *stamp)
+            .map(|entry| &entry.package)
+    }
+
+    pub fn insert(&mut self, dir_name: String, stamp: EntryStamp, package: Package) {
+        self.entries.insert(dir_name, CacheEntry { stamp, package });
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Write to a temporary file first so an interrupted write can't leave a broken cache. Each
+        // process has its own, so pacbrow instances started together don't write over each other.
+        let temp_path = path.with_extension(format!("{}.tmp", process::id()));
+        let writer = BufWriter::new(fs::File::create(&temp_path)?);
+        serde_json::to_writer(writer, self).map_err(io::Error::other)?;
+        fs::rename(temp_path, path)
+    }
+}
+
+// $XDG_CACHE_HOME/pacbrow, falling back to ~/.cache/pacbrow.
+fn cache_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home::home_dir()?.join(".cache"),
+    };
+    Some(base.join("pacbrow"))
+}
+
+// Each database gets its own cache file, so browsing another root doesn't evict the main one. The
+// name is an FNV-1a hash of the dbpath, which unlike std's hashers doesn't change between Rust
+// releases.
+pub fn cache_file(dbpath: &Path) -> Option<PathBuf> {
+    let hash = dbpath
+        .as_os_str()
+        .as_bytes()
+        .iter()
+        .fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+        });
+    Some(cache_dir()?.join(format!("local-{:016x}.json", hash)))
+}
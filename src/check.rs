@@ -0,0 +1,517 @@
+use flate2::read::GzDecoder;
+use sha2::{Digest, Sha256};
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Read},
+    os::unix::fs::{MetadataExt, PermissionsExt},
+    path::Path,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use crate::{config::Paths, localdb::parse_sections, package::Package};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckLevel {
+    // pacman -Qk: every file in the package's file list exists
+    Files,
+    // pacman -Qkk: every file matches the package's mtree data, including its checksum
+    Full,
+}
+impl CheckLevel {
+    pub fn flag(&self) -> &'static str {
+        match self {
+            CheckLevel::Files => "-Qk",
+            CheckLevel::Full => "-Qkk",
+        }
+    }
+}
+
+// Result of checking one package
+pub struct PackageCheck {
+    pub name: String,
+    pub level: CheckLevel,
+    pub total_files: usize,
+    // Number of files with at least one problem
+    pub problem_files: usize,
+    // Each problem file, e.g. "/usr/bin/ls (Size mismatch)"
+    pub problems: Vec<String>,
+    // Set if the package couldn't be checked at all
+    pub error: Option<String>,
+}
+impl PackageCheck {
+    // Lines for the results pane, laid out like pacman's output.
+    pub fn lines(&self) -> Vec<String> {
+        if let Some(error) = &self.error {
+            return vec![format!("{}: {}", self.name, error)];
+        }
+        let mut lines = self
+            .problems
+            .iter()
+            .map(|problem| format!("{}: {}", self.name, problem))
+            .collect::<Vec<String>>();
+        lines.push(format!(
+            "{}: {} total files, {} {} files",
+            self.name,
+            self.total_files,
+            self.problem_files,
+            match self.level {
+                CheckLevel::Files => "missing",
+                CheckLevel::Full => "altered",
+            }
+        ));
+        lines
+    }
+
+    pub fn has_problems(&self) -> bool {
+        self.error.is_some() || !self.problems.is_empty()
+    }
+}
+
+// Check packages on a worker thread, sending each result as it is ready. The channel closes
+// once every package has been checked.
+pub fn spawn_check(
+    packages: Vec<Package>,
+    paths: Paths,
+    level: CheckLevel,
+) -> Receiver<PackageCheck> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for package in &packages {
+            // Stop early if the results are no longer wanted
+            if sender.send(check_package(&paths, package, level)).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+pub fn check_package(paths: &Paths, package: &Package, level: CheckLevel) -> PackageCheck {
+    let mut check = PackageCheck {
+        name: package.name.to_owned(),
+        level,
+        total_files: 0,
+        problem_files: 0,
+        problems: Vec::new(),
+        error: None,
+    };
+    match level {
+        CheckLevel::Files => {
+            check.total_files = package.files.len();
+            for file in &package.files {
+                let path = paths.root.join(file.trim_start_matches('/'));
+                if let Err(e) = fs::symlink_metadata(&path) {
+                    check
+                        .problems
+                        .push(format!("{} ({})", file, error_message(&e)));
+                    check.problem_files += 1;
+                }
+            }
+        }
+        CheckLevel::Full => {
+            let entry_dir = paths
+                .dbpath()
+                .join("local")
+                .join(format!("{}-{}", package.name, package.version));
+            match read_mtree(&entry_dir) {
+                Ok(entries) => {
+                    let backup_files = read_backup_files(&entry_dir);
+                    check.total_files = entries.len();
+                    for entry in &entries {
+                        let path = paths.root.join(entry.path.trim_start_matches('/'));
+                        let is_backup = backup_files.contains(&entry.path);
+                        let problems = check_entry(&path, entry, is_backup);
+                        if !problems.is_empty() {
+                            check.problem_files += 1;
+                        }
+                        for problem in problems {
+                            check.problems.push(format!("{} ({})", entry.path, problem));
+                        }
+                    }
+                }
+                Err(e) => {
+                    check.error = Some(format!("unable to read mtree: {}", error_message(&e)));
+                }
+            }
+        }
+    }
+    check
+}
+
+// Like strerror, without the " (os error N)" Rust adds.
+fn error_message(e: &io::Error) -> String {
+    let message = e.to_string();
+    match message.find(" (os error") {
+        Some(index) => message[..index].to_owned(),
+        None => message,
+    }
+}
+
+// One file described by a package's mtree
+struct MtreeEntry {
+    path: String,
+    file_type: String,
+    mode: Option<u32>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    time: Option<i64>,
+    size: Option<u64>,
+    link: Option<String>,
+    sha256: Option<String>,
+}
+
+fn read_mtree(entry_dir: &Path) -> io::Result<Vec<MtreeEntry>> {
+    let mut mtree = String::new();
+    GzDecoder::new(File::open(entry_dir.join("mtree"))?).read_to_string(&mut mtree)?;
+    Ok(parse_mtree(&mtree))
+}
+
+// Parse mtree text as written by makepkg, applying /set defaults. Package metadata files such as
+// .PKGINFO are skipped since they aren't installed.
+fn parse_mtree(mtree: &str) -> Vec<MtreeEntry> {
+    let mut defaults: HashMap<String, String> = HashMap::new();
+    let mut entries: Vec<MtreeEntry> = Vec::new();
+    for line in mtree.lines() {
+        let mut words = line.split_whitespace();
+        let Some(first) = words.next() else {
+            continue;
+        };
+        let keywords = words.filter_map(|word| word.split_once('='));
+        match first {
+            "/set" => {
+                for (key, value) in keywords {
+                    defaults.insert(key.to_owned(), value.to_owned());
+                }
+            }
+            "/unset" => {
+                for key in line.split_whitespace().skip(1) {
+                    defaults.remove(key);
+                }
+            }
+            path if path.starts_with("./") && !path.starts_with("./.") => {
+                let mut values = defaults.clone();
+                for (key, value) in keywords {
+                    values.insert(key.to_owned(), value.to_owned());
+                }
+                let value = |key: &str| values.get(key).map(String::as_str);
+                entries.push(MtreeEntry {
+                    path: format!("/{}", unescape(&path[2..])),
+                    file_type: value("type").unwrap_or("file").to_owned(),
+                    mode: value("mode").and_then(|mode| u32::from_str_radix(mode, 8).ok()),
+                    uid: value("uid").and_then(|uid| uid.parse().ok()),
+                    gid: value("gid").and_then(|gid| gid.parse().ok()),
+                    // Times are written with a fractional part, e.g. "1705182278.0"
+                    time: value("time")
+                        .and_then(|time| time.split('.').next())
+                        .and_then(|time| time.parse().ok()),
+                    size: value("size").and_then(|size| size.parse().ok()),
+                    link: value("link").map(unescape),
+                    sha256: value("sha256digest").map(str::to_owned),
+                });
+            }
+            _ => {}
+        }
+    }
+    entries
+}
+
+// mtree escapes unusual characters as a backslash and three octal digits, e.g. "\040" for a space.
+fn unescape(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut unescaped: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let octal = bytes
+            .get(index + 1..index + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match (bytes[index], octal) {
+            (b'\\', Some(byte)) => {
+                unescaped.push(byte);
+                index += 4;
+            }
+            (byte, _) => {
+                unescaped.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&unescaped).to_string()
+}
+
+// Files listed under %BACKUP% in the package's desc; their contents are expected to change.
+fn read_backup_files(entry_dir: &Path) -> Vec<String> {
+    let desc = fs::read_to_string(entry_dir.join("desc")).unwrap_or_default();
+    parse_sections(&desc)
+        .into_iter()
+        .filter(|(section, _)| *section == "BACKUP")
+        .flat_map(|(_, values)| values)
+        // Each entry is "<path>\t<md5sum>"
+        .filter_map(|value| value.split('\t').next())
+        .map(|path| format!("/{}", path))
+        .collect()
+}
+
+// Compare a file on disk with its mtree entry, like pacman -Qkk.
+fn check_entry(path: &Path, entry: &MtreeEntry, is_backup: bool) -> Vec<String> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) => return vec![error_message(&e)],
+    };
+    let file_type = metadata.file_type();
+    let type_matches = match entry.file_type.as_str() {
+        "dir" => file_type.is_dir(),
+        "link" => file_type.is_symlink(),
+        _ => file_type.is_file(),
+    };
+    if !type_matches {
+        return vec![String::from("File type mismatch")];
+    }
+
+    let mut problems: Vec<String> = Vec::new();
+    if !file_type.is_symlink()
+        && entry
+            .mode
+            .is_some_and(|mode| mode != metadata.permissions().mode() & 0o7777)
+    {
+        problems.push(String::from("Permissions mismatch"));
+    }
+    if entry.uid.is_some_and(|uid| uid != metadata.uid()) {
+        problems.push(String::from("UID mismatch"));
+    }
+    if entry.gid.is_some_and(|gid| gid != metadata.gid()) {
+        problems.push(String::from("GID mismatch"));
+    }
+    if file_type.is_symlink() {
+        let target = fs::read_link(path).map(|target| target.to_string_lossy().to_string());
+        if entry
+            .link
+            .as_ref()
+            .is_some_and(|link| target.as_ref().ok() != Some(link))
+        {
+            problems.push(String::from("Symlink path mismatch"));
+        }
+    }
+    // Directory times change whenever their contents do, and backup files are meant to be edited
+    if file_type.is_file() && !is_backup {
+        if entry.time.is_some_and(|time| time != metadata.mtime()) {
+            problems.push(String::from("Modification time mismatch"));
+        }
+        if entry.size.is_some_and(|size| size != metadata.len()) {
+            problems.push(String::from("Size mismatch"));
+        } else if let Some(sha256) = &entry.sha256 {
+            match sha256_file(path) {
+                Ok(digest) if digest == *sha256 => {}
+                Ok(_) => problems.push(String::from("SHA-256 checksum mismatch")),
+                Err(e) => problems.push(error_message(&e)),
+            }
+        }
+    }
+    problems
+}
+
+fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        os::unix::fs::symlink,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+
+    // sha256 of "hello\n"
+    const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+    const TIME: u64 = 1705182278;
+
+    fn write_file(root: &Path, path: &str, contents: &str, mode: u32) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(UNIX_EPOCH + Duration::from_secs(TIME))
+            .unwrap();
+    }
+
+    // A fixture root with one installed package, "fixture", whose files have drifted in known ways
+    fn fixture_root() -> (tempfile::TempDir, Paths, Package) {
+        let root = tempfile::tempdir().unwrap();
+        let metadata = fs::metadata(root.path()).unwrap();
+        write_file(root.path(), "usr/bin/good", "hello\n", 0o755);
+        write_file(root.path(), "usr/bin/resized", "hello, world\n", 0o755);
+        write_file(root.path(), "usr/bin/chmodded", "hello\n", 0o644);
+        write_file(root.path(), "usr/bin/corrupt", "HELLO\n", 0o755);
+        write_file(root.path(), "usr/share/with space", "hello\n", 0o644);
+        write_file(root.path(), "etc/fixture.conf", "edited\n", 0o644);
+        symlink("good", root.path().join("usr/bin/link")).unwrap();
+        // Whatever the umask
+        for dir in ["etc", "usr", "usr/bin", "usr/share"] {
+            fs::set_permissions(root.path().join(dir), fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let mtree = format!(
+            "#mtree\n\
+             /set type=file uid={uid} gid={gid} mode=755\n\
+             ./.PKGINFO time={TIME}.0 size=100 sha256digest=00\n\
+             ./etc time={TIME}.0 mode=755 type=dir\n\
+             ./etc/fixture.conf time={TIME}.0 mode=644 size=6 sha256digest={HELLO_SHA256}\n\
+             ./usr time={TIME}.0 type=dir\n\
+             ./usr/bin time={TIME}.0 type=dir\n\
+             ./usr/bin/chmodded time={TIME}.0 size=6 sha256digest={HELLO_SHA256}\n\
+             ./usr/bin/corrupt time={TIME}.0 size=6 sha256digest={HELLO_SHA256}\n\
+             ./usr/bin/good time={TIME}.0 size=6 sha256digest={HELLO_SHA256}\n\
+             ./usr/bin/link time={TIME}.0 mode=777 type=link link=good\n\
+             ./usr/bin/missing time={TIME}.0 size=6 sha256digest={HELLO_SHA256}\n\
+             ./usr/bin/resized time={TIME}.0 size=6 sha256digest={HELLO_SHA256}\n\
+             /set mode=644\n\
+             ./usr/share time={TIME}.0 mode=755 type=dir\n\
+             ./usr/share/with\\040space time={TIME}.0 size=6 sha256digest={HELLO_SHA256}\n",
+            uid = metadata.uid(),
+            gid = metadata.gid(),
+        );
+        let entry_dir = root.path().join("var/lib/pacman/local/fixture-1.0-1");
+        fs::create_dir_all(&entry_dir).unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(mtree.as_bytes()).unwrap();
+        fs::write(entry_dir.join("mtree"), encoder.finish().unwrap()).unwrap();
+        fs::write(
+            entry_dir.join("desc"),
+            "%NAME%\nfixture\n\n%VERSION%\n1.0-1\n\n\
+             %BACKUP%\netc/fixture.conf\tb1946ac92492d2347c6235b4d2611184\n\n",
+        )
+        .unwrap();
+
+        let paths = Paths {
+            root: root.path().to_owned(),
+            ..Paths::default()
+        };
+        let package = Package {
+            name: String::from("fixture"),
+            version: String::from("1.0-1"),
+            files: parse_mtree(&mtree)
+                .into_iter()
+                .map(|entry| entry.path)
+                .collect(),
+            ..Package::default()
+        };
+        (root, paths, package)
+    }
+
+    #[test]
+    fn parses_mtree() {
+        let entries = parse_mtree(
+            "#mtree\n\
+             /set type=file uid=0 gid=0 mode=644\n\
+             ./.BUILDINFO time=1.0 size=1\n\
+             ./usr/bin time=1705182278.0 mode=755 type=dir\n\
+             /unset mode\n\
+             ./usr/bin/a\\134b\\040c time=1705182278.5 size=6 sha256digest=ab\n\
+             ./usr/lib/libacl.so time=1.0 type=link link=libacl.so.1\n",
+        );
+        let summary = entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{} {} {:?} {:?} {:?} {:?} {:?}",
+                    entry.path,
+                    entry.file_type,
+                    entry.mode,
+                    entry.uid,
+                    entry.time,
+                    entry.size,
+                    entry.link
+                )
+            })
+            .collect::<Vec<String>>();
+        assert_eq!(
+            summary,
+            vec![
+                "/usr/bin dir Some(493) Some(0) Some(1705182278) None None",
+                "/usr/bin/a\\b c file None Some(0) Some(1705182278) Some(6) None",
+                "/usr/lib/libacl.so link None Some(0) Some(1) None Some(\"libacl.so.1\")",
+            ]
+        );
+        assert_eq!(entries[1].sha256.as_deref(), Some("ab"));
+    }
+
+    #[test]
+    fn unescapes_octal_sequences() {
+        assert_eq!(unescape("with\\040space"), "with space");
+        assert_eq!(unescape("back\\134slash"), "back\\slash");
+        assert_eq!(unescape("caf\\303\\251"), "café");
+        // Not an escape
+        assert_eq!(unescape("a\\9b\\"), "a\\9b\\");
+    }
+
+    #[test]
+    fn finds_missing_files() {
+        let (_root, paths, package) = fixture_root();
+        let check = check_package(&paths, &package, CheckLevel::Files);
+        assert_eq!(check.total_files, 12);
+        assert_eq!(check.problem_files, 1);
+        assert_eq!(
+            check.problems,
+            vec!["/usr/bin/missing (No such file or directory)"]
+        );
+        assert_eq!(
+            check.lines().last().unwrap(),
+            "fixture: 12 total files, 1 missing files"
+        );
+    }
+
+    #[test]
+    fn finds_altered_files() {
+        let (_root, paths, package) = fixture_root();
+        let check = check_package(&paths, &package, CheckLevel::Full);
+        assert_eq!(check.error, None);
+        assert_eq!(check.total_files, 12);
+        assert_eq!(
+            check.problems,
+            vec![
+                "/usr/bin/chmodded (Permissions mismatch)",
+                "/usr/bin/corrupt (SHA-256 checksum mismatch)",
+                "/usr/bin/missing (No such file or directory)",
+                "/usr/bin/resized (Size mismatch)",
+            ]
+        );
+        assert_eq!(check.problem_files, 4);
+    }
+
+    #[test]
+    fn reports_a_missing_mtree() {
+        let (_root, paths, mut package) = fixture_root();
+        package.version = String::from("2.0-1");
+        let check = check_package(&paths, &package, CheckLevel::Full);
+        assert_eq!(
+            check.lines(),
+            vec!["fixture: unable to read mtree: No such file or directory"]
+        );
+    }
+}
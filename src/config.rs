@@ -15,6 +15,7 @@ trait Config {}
 struct ConfigTomlUser {
     colours: Option<ColoursUser>,
     operation: Option<OperationUser>,
+    paths: Option<PathsUser>,
 }
 impl Config for ConfigTomlUser {}
 
@@ -34,10 +35,21 @@ pub struct OperationUser {
     pub allow_colon_in_search: Option<bool>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct PathsUser {
+    pub root: Option<PathBuf>,
+    pub dbpath: Option<PathBuf>,
+    pub logfile: Option<PathBuf>,
+    pub cachedir: Option<PathBuf>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ConfigToml {
     pub colours: Colours,
     pub operation: Operation,
+    // Configs written before [paths] existed should keep working
+    #[serde(default)]
+    pub paths: Paths,
 }
 impl Config for ConfigToml {}
 
@@ -57,6 +69,40 @@ pub struct Operation {
     pub allow_colon_in_search: bool,
 }
 
+// Each path that isn't set keeps pacman's default.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Paths {
+    pub root: PathBuf,
+    pub dbpath: PathBuf,
+    pub logfile: PathBuf,
+    pub cachedir: PathBuf,
+}
+impl Default for Paths {
+    fn default() -> Self {
+        Self {
+            root: PathBuf::from("/"),
+            dbpath: PathBuf::from("var/lib/pacman"),
+            logfile: PathBuf::from("var/log/pacman.log"),
+            cachedir: PathBuf::from("var/cache/pacman/pkg"),
+        }
+    }
+}
+impl Paths {
+    // A relative dbpath is resolved against the root, like pacman does.
+    pub fn dbpath(&self) -> PathBuf {
+        self.root.join(&self.dbpath)
+    }
+
+    pub fn logfile(&self) -> PathBuf {
+        self.root.join(&self.logfile)
+    }
+
+    pub fn cachedir(&self) -> PathBuf {
+        self.root.join(&self.cachedir)
+    }
+}
+
 // Used for development.
 const DEV_CONFIG_STR: &str = "./config.toml";
 // Default.
@@ -67,12 +113,7 @@ const CONFIG_STR: &str = r".config/pacbrow/config.toml";
 pub fn read_config() -> io::Result<ConfigToml> {
     let home_dir = match home::home_dir() {
         Some(path) if !path.as_os_str().is_empty() => path,
-        _ => {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Unable to locate home dir.",
-            ))
-        }
+        _ => return Err(io::Error::other("Unable to locate home dir.")),
     };
 
     let mut default_config_buf = PathBuf::from(&home_dir);
@@ -85,15 +126,9 @@ pub fn read_config() -> io::Result<ConfigToml> {
         match fs::read_to_string(path) {
             Ok(toml_str) => match toml::from_str(&toml_str) {
                 Ok(config_toml) => Ok(config_toml),
-                Err(e) => Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    format!("{} ({})", e, path.display()),
-                )),
+                Err(e) => Err(io::Error::other(format!("{} ({})", e, path.display()))),
             },
-            Err(e) => Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("{} ({})", e, path.display()),
-            )),
+            Err(e) => Err(io::Error::other(format!("{} ({})", e, path.display()))),
         }
     }
 
@@ -141,8 +176,71 @@ pub fn read_config() -> io::Result<ConfigToml> {
                     config_toml.operation.allow_colon_in_search = allow_colon_in_search;
                 }
             }
+
+            if let Some(paths) = user_conf.paths {
+                if let Some(root) = paths.root {
+                    config_toml.paths.root = root;
+                }
+                if let Some(dbpath) = paths.dbpath {
+                    config_toml.paths.dbpath = dbpath;
+                }
+                if let Some(logfile) = paths.logfile {
+                    config_toml.paths.logfile = logfile;
+                }
+                if let Some(cachedir) = paths.cachedir {
+                    config_toml.paths.cachedir = cachedir;
+                }
+            }
         };
     }
 
     Ok(config_toml)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_default_when_missing() {
+        let config_str = fs::read_to_string(DEV_CONFIG_STR).unwrap();
+        let without_paths = &config_str[..config_str.find("[paths]").unwrap()];
+        let config_toml: ConfigToml = toml::from_str(without_paths).unwrap();
+        assert_eq!(config_toml.paths.dbpath(), PathBuf::from("/var/lib/pacman"));
+        assert_eq!(
+            config_toml.paths.logfile(),
+            PathBuf::from("/var/log/pacman.log")
+        );
+        assert_eq!(
+            config_toml.paths.cachedir(),
+            PathBuf::from("/var/cache/pacman/pkg")
+        );
+    }
+
+    #[test]
+    fn paths_default_individually() {
+        let config_str = fs::read_to_string(DEV_CONFIG_STR).unwrap();
+        let without_paths = &config_str[..config_str.find("[paths]").unwrap()];
+        let config_toml: ConfigToml =
+            toml::from_str(&format!("{}[paths]\nroot = \"/mnt\"\n", without_paths)).unwrap();
+        assert_eq!(
+            config_toml.paths.dbpath(),
+            PathBuf::from("/mnt/var/lib/pacman")
+        );
+        assert_eq!(
+            config_toml.paths.logfile(),
+            PathBuf::from("/mnt/var/log/pacman.log")
+        );
+
+        let config_toml: ConfigToml = toml::from_str(&format!(
+            "{}[paths]\ndbpath = \"/srv/pacman\"\n",
+            without_paths
+        ))
+        .unwrap();
+        assert_eq!(config_toml.paths.dbpath(), PathBuf::from("/srv/pacman"));
+        assert_eq!(
+            config_toml.paths.cachedir(),
+            PathBuf::from("/var/cache/pacman/pkg")
+        );
+    }
+}
@@ -0,0 +1,243 @@
+// Minimal calendar helpers. Timestamps are seconds since the Unix epoch, always in UTC.
+
+// Abbreviated month names as pacman -Qi prints them in English, German, French and Spanish
+const MONTHS: [&[&str]; 12] = [
+    &["Jan", "janv.", "ene"],
+    &["Feb", "févr."],
+    &["Mar", "Mär", "mars"],
+    &["Apr", "avr.", "abr"],
+    &["May", "Mai"],
+    &["Jun", "juin"],
+    &["Jul", "juil."],
+    &["Aug", "août", "ago"],
+    &["Sep", "sept.", "sept"],
+    &["Oct", "Okt"],
+    &["Nov"],
+    &["Dec", "Dez", "déc.", "dic"],
+];
+
+// Days since 1970-01-01 for the given proleptic Gregorian date.
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// Inverse of days_from_civil.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = if days >= 0 { days } else { days - 146096 } / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+pub fn days_in_month(year: i64, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    (days_from_civil(next_year, next_month, 1) - days_from_civil(year, month, 1)) as u32
+}
+
+pub fn timestamp_from_parts(
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+) -> i64 {
+    days_from_civil(year, month, day) * 86400
+        + hour as i64 * 3600
+        + minute as i64 * 60
+        + second as i64
+}
+
+pub fn format_timestamp(timestamp: i64) -> String {
+    let (year, month, day) = civil_from_days(timestamp.div_euclid(86400));
+    let seconds = timestamp.rem_euclid(86400);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+// Parse a date as printed by pacman -Qi, e.g. "Sat Jan 13 20:44:38 2024",
+// "Sat 13 Jan 2024 08:44:38 PM UTC" or "sam. 13 janv. 2024 20:44:38 UTC". The time is assumed
+// to be in UTC.
+pub fn parse_pacman_date(date_str: &str) -> Option<i64> {
+    let mut year = None;
+    let mut month = None;
+    let mut day = None;
+    let mut time = None;
+    let mut pm = None;
+
+    for token in date_str.split_whitespace() {
+        let lowercase = token.to_lowercase();
+        if let Some(index) = MONTHS
+            .iter()
+            .position(|names| names.iter().any(|name| name.to_lowercase() == lowercase))
+        {
+            month = Some(index as u32 + 1);
+        } else if token.contains(':') {
+            let parts = token
+                .split(':')
+                .map(|part| part.parse::<u32>().ok())
+                .collect::<Option<Vec<u32>>>()?;
+            time = match parts[..] {
+                [h, m] => Some((h, m, 0)),
+                [h, m, s] => Some((h, m, s)),
+                _ => return None,
+            };
+        } else if token.eq_ignore_ascii_case("AM") {
+            pm = Some(false);
+        } else if token.eq_ignore_ascii_case("PM") {
+            pm = Some(true);
+        } else if let Ok(number) = token.parse::<i64>() {
+            if token.len() == 4 {
+                year = Some(number);
+            } else if (1..=31).contains(&number) {
+                day = Some(number as u32);
+            }
+        }
+    }
+
+    let (mut hour, minute, second) = time?;
+    match pm {
+        Some(true) if hour < 12 => hour += 12,
+        Some(false) if hour == 12 => hour = 0,
+        _ => {}
+    }
+    Some(timestamp_from_parts(
+        year?, month?, day?, hour, minute, second,
+    ))
+}
+
+// Parse a date like "2024-01-18T10:14:12+0100", as written to pacman.log, or "2014-01-18 10:14"
+// as written before pacman 5.1. The time and offset are optional; without an offset the time is
+// taken to be UTC. Dates that don't exist, such as 2023-02-31, are rejected.
+pub fn parse_iso_date(timestamp: &str) -> Option<i64> {
+    let (date, time) = timestamp
+        .split_once('T')
+        .or_else(|| timestamp.split_once(' '))
+        .unwrap_or((timestamp, "00:00"));
+    let mut date_parts = date.splitn(3, '-');
+    let year = date_parts.next()?.parse::<i64>().ok()?;
+    let month = date_parts.next()?.parse::<u32>().ok()?;
+    let day = date_parts.next()?.parse::<u32>().ok()?;
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+
+    let (time, offset_seconds) = match time.find(['+', '-']) {
+        Some(index) => {
+            let offset = &time[index + 1..];
+            if offset.len() != 4 {
+                return None;
+            }
+            let hours = offset.get(..2)?.parse::<i64>().ok()?;
+            let minutes = offset.get(2..)?.parse::<i64>().ok()?;
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let sign = if time[index..].starts_with('-') {
+                -1
+            } else {
+                1
+            };
+            (&time[..index], sign * (hours * 3600 + minutes * 60))
+        }
+        None => (time, 0),
+    };
+    let mut time_parts = time.split(':').map(|part| part.parse::<u32>().ok());
+    let hour = time_parts.next()??;
+    let minute = time_parts.next()??;
+    let second = time_parts.next().unwrap_or(Some(0))?;
+    if time_parts.next().is_some() || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    Some(timestamp_from_parts(year, month, day, hour, minute, second) - offset_seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_iso_dates() {
+        let base = timestamp_from_parts(2024, 1, 18, 10, 14, 12);
+        assert_eq!(parse_iso_date("2024-01-18T10:14:12+0000"), Some(base));
+        assert_eq!(
+            parse_iso_date("2024-01-18T10:14:12+0100"),
+            Some(base - 3600)
+        );
+        assert_eq!(
+            parse_iso_date("2024-01-18T10:14:12-0130"),
+            Some(base + 5400)
+        );
+        assert_eq!(parse_iso_date("2024-01-18T10:14:12"), Some(base));
+        assert_eq!(parse_iso_date("2024-01-18 10:14"), Some(base - 12));
+        assert_eq!(
+            parse_iso_date("2024-01-18"),
+            Some(timestamp_from_parts(2024, 1, 18, 0, 0, 0))
+        );
+        assert_eq!(
+            parse_iso_date("2024-02-29"),
+            Some(timestamp_from_parts(2024, 2, 29, 0, 0, 0))
+        );
+    }
+
+    #[test]
+    fn rejects_impossible_dates() {
+        for date in [
+            "2023-02-29",
+            "2023-02-31",
+            "2024-04-31",
+            "2024-00-10",
+            "2024-13-01",
+            "2024-01-00",
+            "2024-01-18T24:00",
+            "2024-01-18T10:60",
+            "2024-01-18T10:14:60",
+            "2024-01-18T10:14:12:00",
+            "2024-01-18T10:00+2400",
+            "2024-01-18T10:00+0160",
+            "2024-01-18T10:00+01",
+            "2024-01-01T10:00+1é1",
+            "2024-01-01T10:00+é11",
+            "2024-01-é1",
+            "",
+        ] {
+            assert_eq!(parse_iso_date(date), None, "{}", date);
+        }
+    }
+
+    #[test]
+    fn counts_days_in_month() {
+        assert_eq!(days_in_month(2023, 2), 28);
+        assert_eq!(days_in_month(2024, 2), 29);
+        assert_eq!(days_in_month(1900, 2), 28);
+        assert_eq!(days_in_month(2000, 2), 29);
+        assert_eq!(days_in_month(2024, 4), 30);
+        assert_eq!(days_in_month(2024, 12), 31);
+    }
+}
@@ -0,0 +1,217 @@
+use std::cmp::Ordering;
+
+use crate::{
+    package::{dep_name, Package},
+    provides::ProvidesIndex,
+    vercmp::vercmp,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Constraint {
+    Any,
+    Eq,
+    Ge,
+    Le,
+    Gt,
+    Lt,
+}
+
+// A dependency such as "python>=3.12", split into its name and version constraint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub name: String,
+    pub constraint: Constraint,
+    pub version: String,
+}
+impl Dependency {
+    pub fn parse(dep: &str) -> Self {
+        let name = dep_name(dep);
+        let rest = &dep[name.len()..];
+        let (constraint, version) = [
+            (">=", Constraint::Ge),
+            ("<=", Constraint::Le),
+            ("=", Constraint::Eq),
+            (">", Constraint::Gt),
+            ("<", Constraint::Lt),
+        ]
+        .into_iter()
+        .find_map(|(operator, constraint)| {
+            rest.strip_prefix(operator)
+                .map(|version| (constraint, version))
+        })
+        .unwrap_or((Constraint::Any, ""));
+        Self {
+            name: name.to_owned(),
+            constraint,
+            version: version.to_owned(),
+        }
+    }
+
+    pub fn allows_version(&self, version: &str) -> bool {
+        let ordering = vercmp(version, &self.version);
+        match self.constraint {
+            Constraint::Any => true,
+            Constraint::Eq => ordering == Ordering::Equal,
+            Constraint::Ge => ordering != Ordering::Less,
+            Constraint::Le => ordering != Ordering::Greater,
+            Constraint::Gt => ordering == Ordering::Greater,
+            Constraint::Lt => ordering == Ordering::Less,
+        }
+    }
+
+    // Whether the package satisfies the dependency, by name or by one of its provisions, with
+    // pacman's rules: a provision without a version only satisfies unversioned dependencies.
+    pub fn is_satisfied_by(&self, package: &Package) -> bool {
+        (package.name == self.name && self.allows_version(&package.version))
+            || package.provides.iter().any(|provision| {
+                let provision = Dependency::parse(provision);
+                provision.name == self.name
+                    && match (self.constraint, provision.constraint) {
+                        (Constraint::Any, _) => true,
+                        (_, Constraint::Eq) => self.allows_version(&provision.version),
+                        _ => false,
+                    }
+            })
+    }
+}
+
+// The installed package satisfying `dep`, preferring one whose version meets the constraint. The
+// flag is false if only packages with the wrong version were found.
+pub fn find_satisfier<'a>(
+    provides_index: &ProvidesIndex<'a>,
+    dep: &str,
+) -> Option<(&'a Package, bool)> {
+    let dependency = Dependency::parse(dep);
+    let candidates = provides_index.satisfiers(dep);
+    candidates
+        .iter()
+        .find(|package| dependency.is_satisfied_by(package))
+        .map(|package| (*package, true))
+        .or_else(|| candidates.first().map(|package| (*package, false)))
+}
+
+// A dependency of an installed package that nothing installed satisfies
+pub struct BrokenDependency {
+    pub dependency: String,
+    // Installed packages with the right name but the wrong version, e.g. "python 3.11.8-1" or
+    // "acl provides libacl.so=1-64"
+    pub candidates: Vec<String>,
+}
+impl BrokenDependency {
+    // e.g. "python>=3.12 (installed: python 3.11.8-1)" or "java-runtime (not installed)"
+    pub fn description(&self) -> String {
+        if self.candidates.is_empty() {
+            format!("{} (not installed)", self.dependency)
+        } else {
+            format!(
+                "{} (installed: {})",
+                self.dependency,
+                self.candidates.join(", ")
+            )
+        }
+    }
+}
+
+// The dependencies of `package` not satisfied by any installed package, like pacman -Dk.
+pub fn broken_dependencies(
+    package: &Package,
+    provides_index: &ProvidesIndex,
+) -> Vec<BrokenDependency> {
+    package
+        .depends
+        .iter()
+        .filter(|dep| !matches!(find_satisfier(provides_index, dep), Some((_, true))))
+        .map(|dep| BrokenDependency {
+            dependency: dep.to_owned(),
+            candidates: provides_index
+                .satisfiers(dep)
+                .iter()
+                .map(|candidate| {
+                    let name = dep_name(dep);
+                    if candidate.name == name {
+                        format!("{} {}", candidate.name, candidate.version)
+                    } else {
+                        let provisions = candidate
+                            .provides
+                            .iter()
+                            .filter(|provision| dep_name(provision) == name)
+                            .map(String::as_str)
+                            .collect::<Vec<&str>>();
+                        format!("{} provides {}", candidate.name, provisions.join(", "))
+                    }
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, version: &str, provides: &[&str]) -> Package {
+        Package {
+            name: name.to_owned(),
+            version: version.to_owned(),
+            provides: provides
+                .iter()
+                .map(|provision| provision.to_string())
+                .collect(),
+            ..Package::default()
+        }
+    }
+
+    #[test]
+    fn parses_dependencies() {
+        for (dep, name, constraint, version) in [
+            ("glibc", "glibc", Constraint::Any, ""),
+            ("python>=3.12", "python", Constraint::Ge, "3.12"),
+            ("python<=3.12", "python", Constraint::Le, "3.12"),
+            ("libacl.so=1-64", "libacl.so", Constraint::Eq, "1-64"),
+            ("linux>6.1", "linux", Constraint::Gt, "6.1"),
+            ("openssl<3", "openssl", Constraint::Lt, "3"),
+        ] {
+            assert_eq!(
+                Dependency::parse(dep),
+                Dependency {
+                    name: name.to_owned(),
+                    constraint,
+                    version: version.to_owned(),
+                },
+                "{}",
+                dep
+            );
+        }
+    }
+
+    #[test]
+    fn checks_versions() {
+        let python = package("python", "3.12.3-1", &[]);
+        assert!(Dependency::parse("python").is_satisfied_by(&python));
+        assert!(Dependency::parse("python>=3.12").is_satisfied_by(&python));
+        assert!(!Dependency::parse("python>=3.13").is_satisfied_by(&python));
+        assert!(Dependency::parse("python<3.13").is_satisfied_by(&python));
+        assert!(!Dependency::parse("python<3.12").is_satisfied_by(&python));
+        assert!(Dependency::parse("python=3.12.3-1").is_satisfied_by(&python));
+        assert!(!Dependency::parse("python=3.12.2-1").is_satisfied_by(&python));
+        assert!(!Dependency::parse("perl").is_satisfied_by(&python));
+    }
+
+    #[test]
+    fn checks_provisions() {
+        // A versioned dependency needs a provision with "="
+        let acl = package("acl", "2.3.2-1", &["libacl.so=1-64"]);
+        assert!(Dependency::parse("libacl.so").is_satisfied_by(&acl));
+        assert!(Dependency::parse("libacl.so=1-64").is_satisfied_by(&acl));
+        assert!(Dependency::parse("libacl.so>=1").is_satisfied_by(&acl));
+        assert!(!Dependency::parse("libacl.so=1-32").is_satisfied_by(&acl));
+        assert!(!Dependency::parse("libacl.so<1").is_satisfied_by(&acl));
+
+        // An unversioned provision only satisfies unversioned dependencies, even when the
+        // package itself has a matching version
+        let jre = package("jre-openjdk", "21.0.3-1", &["java-runtime"]);
+        assert!(Dependency::parse("java-runtime").is_satisfied_by(&jre));
+        assert!(!Dependency::parse("java-runtime>=17").is_satisfied_by(&jre));
+        assert!(!Dependency::parse("java-runtime=21.0.3-1").is_satisfied_by(&jre));
+    }
+}
@@ -0,0 +1,384 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    depends::find_satisfier,
+    package::{format_size, InstallReason, Package},
+    provides::ProvidesIndex,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TreeDirection {
+    // What the package depends on
+    Depends,
+    // What requires or optionally requires the package, up to the explicitly installed packages
+    // that pull it in
+    RequiredBy,
+}
+
+// One visible line of a package's dependency tree.
+pub struct DepTreeLine {
+    pub text: String,
+    // Dependencies leading from the root to this line, joined by '/'. Identifies the line even
+    // when the same package appears several times.
+    pub node: String,
+    pub expandable: bool,
+}
+
+// Lay out a package's dependencies, or the packages depending on it, as an indented tree,
+// resolving each one against `packages`. The root is always expanded; other lines only show their
+// children if their node is in `expanded`. A package's children are only listed the first time it
+// appears, so later appearances are marked as shown above, and links back to a package's own
+// ancestors as cycles.
+pub fn dep_tree_lines(
+    root: &Package,
+    packages: &[Package],
+    direction: TreeDirection,
+    expanded: &HashSet<String>,
+) -> Vec<DepTreeLine> {
+    let mut tree = DepTree {
+        provides_index: ProvidesIndex::new(packages),
+        packages_by_name: packages
+            .iter()
+            .map(|package| (package.name.as_str(), package))
+            .collect(),
+        direction,
+        expanded,
+        shown: HashSet::from([root.name.as_str()]),
+        ancestors: vec![root.name.as_str()],
+        lines: vec![DepTreeLine {
+            text: format!(
+                "{}{}",
+                package_label(root),
+                if root.installed {
+                    ""
+                } else {
+                    " [not installed]"
+                }
+            ),
+            node: root.name.to_owned(),
+            expandable: false,
+        }],
+    };
+    tree.add_children(root, &root.name, 1);
+    tree.lines
+}
+
+// A package linked to another in the tree's direction
+struct Link<'a> {
+    // As written, e.g. "python>=3.12"
+    name: &'a str,
+    // The installed package it resolves to
+    package: Option<&'a Package>,
+    optional: bool,
+    // False if the package's version doesn't meet the dependency's constraint
+    satisfied: bool,
+}
+
+struct DepTree<'a> {
+    provides_index: ProvidesIndex<'a>,
+    packages_by_name: HashMap<&'a str, &'a Package>,
+    direction: TreeDirection,
+    expanded: &'a HashSet<String>,
+    // Packages already given a line
+    shown: HashSet<&'a str>,
+    // Packages from the root down to the current line
+    ancestors: Vec<&'a str>,
+    lines: Vec<DepTreeLine>,
+}
+impl<'a> DepTree<'a> {
+    fn links(&self, package: &'a Package) -> Vec<Link<'a>> {
+        match self.direction {
+            TreeDirection::Depends => package
+                .depends
+                .iter()
+                .map(|dep| {
+                    let satisfier = find_satisfier(&self.provides_index, dep);
+                    Link {
+                        name: dep,
+                        package: satisfier.map(|(package, _)| package),
+                        optional: false,
+                        satisfied: satisfier.is_some_and(|(_, satisfied)| satisfied),
+                    }
+                })
+                .collect(),
+            TreeDirection::RequiredBy => package
+                .required_by
+                .iter()
+                .map(|name| (name, false))
+                .chain(package.optional_for.iter().map(|name| (name, true)))
+                .map(|(name, optional)| {
+                    let package = self.packages_by_name.get(name.as_str()).copied();
+                    Link {
+                        name,
+                        package,
+                        optional,
+                        satisfied: package.is_some(),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    // Whether the tree stops at `package` rather than listing its links
+    fn is_end(&self, package: &Package) -> bool {
+        self.links(package).is_empty()
+            || self.direction == TreeDirection::RequiredBy
+                && package.install_reason == InstallReason::Explicit
+    }
+
+    fn add_children(&mut self, package: &'a Package, node: &str, depth: usize) {
+        for link in self.links(package) {
+            let dep = link.name;
+            let node = format!("{}/{}", node, dep);
+            let indent = "  ".repeat(depth);
+            let optional = if link.optional { " [optional]" } else { "" };
+            let Some(satisfier) = link.package else {
+                self.lines.push(DepTreeLine {
+                    text: format!("{}  {}{} [not installed]", indent, dep, optional),
+                    node,
+                    expandable: false,
+                });
+                continue;
+            };
+
+            // Name the dependency as written if it has a constraint or is satisfied by a provision
+            let label = if dep == satisfier.name {
+                package_label(satisfier)
+            } else {
+                format!("{}: {}", dep, package_label(satisfier))
+            };
+            let unsatisfied = if link.satisfied {
+                ""
+            } else {
+                " [version not satisfied]"
+            };
+            let status = if self.ancestors.contains(&satisfier.name.as_str()) {
+                Some(" [cycle]")
+            } else if self.direction == TreeDirection::RequiredBy
+                && satisfier.install_reason == InstallReason::Explicit
+            {
+                Some(" [explicitly installed]")
+            } else if self.shown.contains(satisfier.name.as_str()) {
+                Some(" [shown above]")
+            } else {
+                None
+            };
+            let expandable = status.is_none() && !self.is_end(satisfier);
+            let is_expanded = expandable && self.expanded.contains(&node);
+            let marker = match (expandable, is_expanded) {
+                (true, true) => "▾ ",
+                (true, false) => "▸ ",
+                (false, _) => "  ",
+            };
+            self.shown.insert(&satisfier.name);
+            self.lines.push(DepTreeLine {
+                text: format!(
+                    "{}{}{}{}{}{}",
+                    indent,
+                    marker,
+                    label,
+                    optional,
+                    unsatisfied,
+                    status.unwrap_or_default()
+                ),
+                node: node.to_owned(),
+                expandable,
+            });
+
+            if is_expanded {
+                self.ancestors.push(&satisfier.name);
+                self.add_children(satisfier, &node, depth + 1);
+                self.ancestors.pop();
+            }
+        }
+    }
+}
+
+// e.g. "glibc 2.39-1 (45.78 MiB)"
+fn package_label(package: &Package) -> String {
+    format!(
+        "{} {} ({})",
+        package.name,
+        package.version,
+        format_size(package.installed_size)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{localdb::link_reverse_deps, package::OptionalDep};
+    use pretty_assertions::assert_eq;
+
+    fn package(name: &str, depends: &[&str], provides: &[&str]) -> Package {
+        Package {
+            name: name.to_owned(),
+            version: String::from("1-1"),
+            install_reason: InstallReason::Dependency,
+            installed: true,
+            depends: depends.iter().map(|dep| dep.to_string()).collect(),
+            provides: provides
+                .iter()
+                .map(|provision| provision.to_string())
+                .collect(),
+            ..Package::default()
+        }
+    }
+
+    fn packages() -> Vec<Package> {
+        let mut packages = vec![
+            Package {
+                install_reason: InstallReason::Explicit,
+                optional_deps: vec![OptionalDep::parse("viewer: to show reports")],
+                ..package("app", &["lib", "sh", "util>=2", "gone"], &[])
+            },
+            // lib and libc depend on each other
+            package("lib", &["libc"], &[]),
+            package("libc", &["lib"], &[]),
+            package("bash", &["libc"], &["sh"]),
+            package("util", &[], &[]),
+            // Only pulled in by an optional dependency
+            package("viewer", &["render"], &[]),
+            package("render", &[], &[]),
+        ];
+        link_reverse_deps(&mut packages);
+        packages
+    }
+
+    fn tree(root: &str, direction: TreeDirection, expanded: &[&str]) -> Vec<(String, String)> {
+        let packages = packages();
+        let root = packages
+            .iter()
+            .find(|package| package.name == root)
+            .unwrap();
+        let expanded = expanded.iter().map(|node| node.to_string()).collect();
+        dep_tree_lines(root, &packages, direction, &expanded)
+            .into_iter()
+            .map(|line| (line.node, line.text))
+            .collect()
+    }
+
+    fn lines(lines: &[(&str, &str)]) -> Vec<(String, String)> {
+        lines
+            .iter()
+            .map(|(node, text)| (node.to_string(), text.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn lists_dependencies() {
+        assert_eq!(
+            tree("app", TreeDirection::Depends, &[]),
+            lines(&[
+                ("app", "app 1-1 (0.00 B)"),
+                ("app/lib", "  ▸ lib 1-1 (0.00 B)"),
+                // Provided by another package
+                ("app/sh", "  ▸ sh: bash 1-1 (0.00 B)"),
+                (
+                    "app/util>=2",
+                    "    util>=2: util 1-1 (0.00 B) [version not satisfied]"
+                ),
+                ("app/gone", "    gone [not installed]"),
+            ])
+        );
+    }
+
+    #[test]
+    fn marks_cycles_and_repeats() {
+        assert_eq!(
+            tree(
+                "app",
+                TreeDirection::Depends,
+                &["app/lib", "app/lib/libc", "app/sh"]
+            ),
+            lines(&[
+                ("app", "app 1-1 (0.00 B)"),
+                ("app/lib", "  ▾ lib 1-1 (0.00 B)"),
+                ("app/lib/libc", "    ▾ libc 1-1 (0.00 B)"),
+                ("app/lib/libc/lib", "        lib 1-1 (0.00 B) [cycle]"),
+                ("app/sh", "  ▾ sh: bash 1-1 (0.00 B)"),
+                ("app/sh/libc", "      libc 1-1 (0.00 B) [shown above]"),
+                (
+                    "app/util>=2",
+                    "    util>=2: util 1-1 (0.00 B) [version not satisfied]"
+                ),
+                ("app/gone", "    gone [not installed]"),
+            ])
+        );
+    }
+
+    #[test]
+    fn expands_by_node_path() {
+        // Expanding a line deeper down shows nothing while its parent is collapsed
+        assert_eq!(
+            tree("app", TreeDirection::Depends, &["app/lib/libc"]),
+            tree("app", TreeDirection::Depends, &[])
+        );
+        // Nodes name the path to a line, not just the package
+        assert_eq!(
+            tree("app", TreeDirection::Depends, &["lib", "libc"]),
+            tree("app", TreeDirection::Depends, &[])
+        );
+        assert_eq!(
+            tree("app", TreeDirection::Depends, &["app/lib"])[1..3],
+            lines(&[
+                ("app/lib", "  ▾ lib 1-1 (0.00 B)"),
+                ("app/lib/libc", "    ▸ libc 1-1 (0.00 B)"),
+            ])
+        );
+    }
+
+    #[test]
+    fn lists_what_requires_a_package() {
+        assert_eq!(
+            tree("libc", TreeDirection::RequiredBy, &[]),
+            lines(&[
+                ("libc", "libc 1-1 (0.00 B)"),
+                ("libc/bash", "  ▸ bash 1-1 (0.00 B)"),
+                ("libc/lib", "  ▸ lib 1-1 (0.00 B)"),
+            ])
+        );
+        // The tree stops at explicitly installed packages
+        assert_eq!(
+            tree(
+                "libc",
+                TreeDirection::RequiredBy,
+                &["libc/bash", "libc/lib"]
+            ),
+            lines(&[
+                ("libc", "libc 1-1 (0.00 B)"),
+                ("libc/bash", "  ▾ bash 1-1 (0.00 B)"),
+                (
+                    "libc/bash/app",
+                    "      app 1-1 (0.00 B) [explicitly installed]"
+                ),
+                ("libc/lib", "  ▾ lib 1-1 (0.00 B)"),
+                (
+                    "libc/lib/app",
+                    "      app 1-1 (0.00 B) [explicitly installed]"
+                ),
+                ("libc/lib/libc", "      libc 1-1 (0.00 B) [cycle]"),
+            ])
+        );
+        assert_eq!(
+            tree("app", TreeDirection::RequiredBy, &[]),
+            lines(&[("app", "app 1-1 (0.00 B)")])
+        );
+    }
+
+    #[test]
+    fn follows_optional_dependencies_up() {
+        // viewer is only optionally required, but the tree still leads up to app through it
+        assert_eq!(
+            tree("render", TreeDirection::RequiredBy, &["render/viewer"]),
+            lines(&[
+                ("render", "render 1-1 (0.00 B)"),
+                ("render/viewer", "  ▾ viewer 1-1 (0.00 B)"),
+                (
+                    "render/viewer/app",
+                    "      app 1-1 (0.00 B) [optional] [explicitly installed]"
+                ),
+            ])
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use crate::{
+    package::{info_line, Package},
+    vercmp::vercmp,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Removed,
+    Upgraded,
+    Downgraded,
+}
+impl Change {
+    // Shown before the package name in the list pane
+    pub fn marker(&self) -> &'static str {
+        match self {
+            Change::Added => "+",
+            Change::Removed => "-",
+            Change::Upgraded => "↑",
+            Change::Downgraded => "↓",
+        }
+    }
+}
+
+// A package that differs between the old and new package sets.
+pub struct DiffEntry {
+    pub change: Change,
+    pub old: Option<Package>,
+    pub new: Option<Package>,
+}
+impl DiffEntry {
+    // The package as it is in the new set, or in the old one if it was removed
+    pub fn package(&self) -> &Package {
+        self.new.as_ref().or(self.old.as_ref()).unwrap()
+    }
+
+    pub fn summary(&self) -> String {
+        match (self.change, &self.old, &self.new) {
+            (Change::Added, _, Some(new)) => format!("added {}", new.version),
+            (Change::Removed, Some(old), _) => format!("removed {}", old.version),
+            (Change::Upgraded | Change::Downgraded, Some(old), Some(new)) => format!(
+                "{} {} -> {}",
+                if self.change == Change::Upgraded {
+                    "upgraded"
+                } else {
+                    "downgraded"
+                },
+                old.version,
+                new.version
+            ),
+            _ => String::new(),
+        }
+    }
+
+    // Field-by-field comparison. Unchanged fields are indented; changed fields are shown twice,
+    // prefixed with "-" for the old value and "+" for the new one.
+    pub fn info_lines(&self) -> Vec<String> {
+        let old_fields = self
+            .old
+            .as_ref()
+            .map(|package| package.info_fields())
+            .unwrap_or_default();
+        let new_fields = self
+            .new
+            .as_ref()
+            .map(|package| package.info_fields())
+            .unwrap_or_default();
+
+        let mut labels: Vec<&str> = new_fields.iter().map(|(label, _)| *label).collect();
+        for (label, _) in &old_fields {
+            if !labels.contains(label) {
+                labels.push(label);
+            }
+        }
+
+        let field = |fields: &[(&'static str, Vec<String>)], label: &str| {
+            fields
+                .iter()
+                .find(|(field_label, _)| *field_label == label)
+                .map(|(_, values)| values.to_owned())
+        };
+        let field_lines = |prefix: &str, label: &str, values: &[String]| {
+            values
+                .iter()
+                .enumerate()
+                .map(|(index, value)| {
+                    format!(
+                        "{} {}",
+                        prefix,
+                        info_line(if index == 0 { label } else { "" }, value)
+                    )
+                })
+                .collect::<Vec<String>>()
+        };
+
+        let mut lines: Vec<String> = Vec::new();
+        for label in labels {
+            match (field(&old_fields, label), field(&new_fields, label)) {
+                (Some(old), Some(new)) if old == new => lines.extend(field_lines(" ", label, &new)),
+                (old, new) => {
+                    if let Some(old) = old {
+                        lines.extend(field_lines("-", label, &old));
+                    }
+                    if let Some(new) = new {
+                        lines.extend(field_lines("+", label, &new));
+                    }
+                }
+            }
+        }
+        lines
+    }
+}
+
+// Compare two package sets by name, keeping only the packages that were added, removed, or
+// changed version. The result is sorted by name.
+pub fn diff_packages(old: Vec<Package>, new: Vec<Package>) -> Vec<DiffEntry> {
+    let mut old_by_name: HashMap<String, Package> = old
+        .into_iter()
+        .map(|package| (package.name.to_owned(), package))
+        .collect();
+
+    let mut entries: Vec<DiffEntry> = Vec::new();
+    for package in new {
+        let entry = match old_by_name.remove(&package.name) {
+            None => DiffEntry {
+                change: Change::Added,
+                old: None,
+                new: Some(package),
+            },
+            Some(old_package) => {
+                let change = match vercmp(&old_package.version, &package.version) {
+                    Ordering::Less => Change::Upgraded,
+                    Ordering::Greater => Change::Downgraded,
+                    Ordering::Equal => continue,
+                };
+                DiffEntry {
+                    change,
+                    old: Some(old_package),
+                    new: Some(package),
+                }
+            }
+        };
+        entries.push(entry);
+    }
+    entries.extend(old_by_name.into_values().map(|package| DiffEntry {
+        change: Change::Removed,
+        old: Some(package),
+        new: None,
+    }));
+    entries.sort_by(|a, b| a.package().name.cmp(&b.package().name));
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::package::OptionalDep;
+
+    fn package(name: &str, version: &str) -> Package {
+        Package {
+            name: name.to_owned(),
+            version: version.to_owned(),
+            ..Package::default()
+        }
+    }
+
+    #[test]
+    fn classifies_changes() {
+        let old = vec![
+            package("acl", "2.3.1-1"),
+            package("glibc", "2.39-1"),
+            package("vim", "9.1.0-1"),
+            package("xz", "5.4.9-1"),
+            package("zsh", "5.9-1"),
+        ];
+        let new = vec![
+            package("bash", "5.2.026-2"),
+            package("acl", "2.3.2-1"),
+            package("glibc", "2.39-1"),
+            package("vim", "9.0.2-1"),
+            // Newer with pacman's rules, though not as plain strings
+            package("xz", "5.4.10-1"),
+        ];
+        let entries = diff_packages(old, new);
+        let changes = entries
+            .iter()
+            .map(|entry| (entry.package().name.as_str(), entry.change, entry.summary()))
+            .collect::<Vec<(&str, Change, String)>>();
+        assert_eq!(
+            changes,
+            vec![
+                (
+                    "acl",
+                    Change::Upgraded,
+                    String::from("upgraded 2.3.1-1 -> 2.3.2-1")
+                ),
+                ("bash", Change::Added, String::from("added 5.2.026-2")),
+                (
+                    "vim",
+                    Change::Downgraded,
+                    String::from("downgraded 9.1.0-1 -> 9.0.2-1")
+                ),
+                (
+                    "xz",
+                    Change::Upgraded,
+                    String::from("upgraded 5.4.9-1 -> 5.4.10-1")
+                ),
+                ("zsh", Change::Removed, String::from("removed 5.9-1")),
+            ]
+        );
+    }
+
+    #[test]
+    fn compares_fields() {
+        let old = Package {
+            description: String::from("Access control list utilities"),
+            optional_deps: vec![OptionalDep::parse("perl: for scripts")],
+            ..package("acl", "2.3.1-1")
+        };
+        let new = Package {
+            description: String::from("Access control list utilities"),
+            ..package("acl", "2.3.2-1")
+        };
+        let entries = diff_packages(vec![old], vec![new]);
+        let lines = entries[0].info_lines();
+        let line = |prefix: &str, label: &str, value: &str| {
+            format!("{} {}", prefix, info_line(label, value))
+        };
+        // Unchanged fields are shown once, changed ones with their old and new values
+        assert_eq!(lines[0], line(" ", "Name", "acl"));
+        assert_eq!(lines[1], line("-", "Version", "2.3.1-1"));
+        assert_eq!(lines[2], line("+", "Version", "2.3.2-1"));
+        assert_eq!(
+            lines[3],
+            line(" ", "Description", "Access control list utilities")
+        );
+        assert!(lines.contains(&line("-", "Optional Deps", "perl: for scripts")));
+        assert!(lines.contains(&line("+", "Optional Deps", "None")));
+
+        // Added and removed packages only have one side
+        let entries = diff_packages(vec![], vec![package("bash", "5.2.026-2")]);
+        assert!(entries[0]
+            .info_lines()
+            .iter()
+            .all(|line| line.starts_with('+')));
+    }
+}
@@ -4,6 +4,17 @@ Many commands can be combined; e.g., :qp prints the current package list and exi
 :p,:print               Print current package list
 :c,:commands            Display list of commands
 :h,:help                Open help page
+:f,:files [PATTERN]     Show the selected package's files, optionally only paths containing PATTERN
+:history                Show the selected package's install, upgrade and removal history
+:impact                 Show what removing the selected package with pacman -Rs would remove, which installed packages would break, and the space freed
+:provides NAME          List every installed package providing NAME, such as the providers of a virtual package like sh
+:rollback DATE [FILE]   Show how to restore the packages installed at DATE (YYYY-MM-DD or YYYY-MM-DDTHH:MM, UTC) from the package cache, optionally saving a pacman -U plan to FILE. The plan is never run
+:sort [-]KEY            Sort the package list by name, version, size or date (installed), descending if KEY starts with -
+:owns PATH              Jump to the package that owns the file at PATH and show it in the package's files
+:qk [all]               Check that the selected package's files exist, like pacman -Qk, or those of every listed package with \"all\"
+:qkk [all]              Check the selected package's files against its mtree data, like pacman -Qkk, including checksums
+:x,:export FILE         Export the current package list to FILE as JSON, CSV or TOML, chosen by the file extension
+:dot FILE [OPTIONS]     Write the selected package's dependency graph to FILE in Graphviz DOT format. Options: all (graph every listed package), reverse (follow the packages requiring it instead), optional (include optional dependencies), depth=N (follow at most N links)
 -------
 ";
 
@@ -30,6 +41,21 @@ DISPLAY MODE: You enter display mode when you are viewing a non-package-related
 -------
 
 
+--DIFF--
+When pacbrow is started with --diff, the package list only contains packages that differ between the two package sets, each marked with how it changed: + added, - removed, ↑ upgraded, ↓ downgraded. The info pane compares the package's fields; changed fields are shown twice, with the old value marked - and the new value marked +.
+-------
+
+
+--ORPHANS--
+When pacbrow is started with --orphans, the package list contains every dependency that could be removed. pacman -Qdt only finds dependencies nothing requires; pacbrow repeats that check, so packages only required by those orphans are listed too, and so on until no more turn up. The title shows the space they take up, and the info pane shows why each package is orphaned.
+-------
+
+
+--BROKEN DEPENDENCIES--
+When pacbrow is started with --broken, the package list contains every package with a dependency that nothing installed satisfies, like pacman -Dk. Version constraints such as python>=3.12 are checked against installed versions and against what packages provide. The info pane lists each unsatisfied dependency and what is installed in its place, and the dependency tree marks versions that don't satisfy their constraint.
+-------
+
+
 --CONTROLS--
 NORMAL MODE
 :                       Enter command mode
@@ -43,7 +69,15 @@ g                       Jump to top of package list
 G                       Jump to bottom of package list
 y                       Copy currently-selected package name to clipboard
 Y                       Copy current package list to clipboard
+f                       Switch the info pane between package info and files
+H                       Switch the info pane between package info and history
+K                       Switch the info pane between package info and integrity check results
+D                       Switch the info pane between package info and the dependency tree
+R                       Switch the info pane between package info and the reverse dependency tree
+X                       Switch the info pane between package info and what removing the package would do
 l,i,<Right>,<Enter>     Enter info mode for the currently selected package
+<C-o>                   Go back to the package you were on before following a link
+<C-i>,<Tab>             Go forward again to the package you went back from
 
 COMMAND MODE
 The list of commands can be found in the \"--COMMANDS--\" section below.
@@ -69,8 +103,25 @@ u                       Scroll 10 lines up this package's information
 d                       Scroll 10 lines down this package's information
 g                       Jump to top of package info
 G                       Jump to bottom of package info
-y                       Copy current line of package info to clipboard
+w                       Select the next package named on the current line, e.g. in \"Depends On\"
+b                       Select the previous package named on the current line
+<Enter>                 Jump to the selected package, or to the providers of a virtual package (package info)
+<C-o>                   Go back to the package you were on before the last jump
+<C-i>,<Tab>             Go forward again to the package you went back from
+y                       Copy current line of package info (or current file path) to clipboard
 Y                       Copy package info to clipboard
+f                       Switch between package info and files
+H                       Switch between package info and the package's history from the pacman log
+K                       Switch between package info and integrity check results
+D                       Switch between package info and the package's dependency tree
+R                       Switch between package info and the packages requiring this one, up to those explicitly installed
+X                       Switch between package info and the impact of removing the package: what pacman -Rs would remove with it, what would break, and the space freed
+o,<Enter>               Fold or unfold the directory under the cursor (files view)
+O                       Unfold all directories (files view)
+C                       Fold all directories (files view)
+o,<Enter>               Expand or collapse the dependency under the cursor (dependency trees)
+O                       Expand every dependency (dependency trees)
+C                       Collapse every dependency (dependency trees)
 
 DISPLAY MODE
 <Esc>                   Enter normal mode, closing the display
@@ -90,6 +141,17 @@ Many commands can be combined; e.g., :qp prints the current package list and exi
 :p,:print               Print current package list
 :c,:commands            Display list of commands
 :h,:help                Open help page
+:f,:files [PATTERN]     Show the selected package's files, optionally only paths containing PATTERN
+:history                Show the selected package's install, upgrade and removal history
+:impact                 Show what removing the selected package with pacman -Rs would remove, which installed packages would break, and the space freed
+:provides NAME          List every installed package providing NAME, such as the providers of a virtual package like sh
+:rollback DATE [FILE]   Show how to restore the packages installed at DATE (YYYY-MM-DD or YYYY-MM-DDTHH:MM, UTC) from the package cache, optionally saving a pacman -U plan to FILE. The plan is never run
+:sort [-]KEY            Sort the package list by name, version, size or date (installed), descending if KEY starts with -
+:owns PATH              Jump to the package that owns the file at PATH and show it in the package's files
+:qk [all]               Check that the selected package's files exist, like pacman -Qk, or those of every listed package with \"all\"
+:qkk [all]              Check the selected package's files against its mtree data, like pacman -Qkk, including checksums
+:x,:export FILE         Export the current package list to FILE as JSON, CSV or TOML, chosen by the file extension
+:dot FILE [OPTIONS]     Write the selected package's dependency graph to FILE in Graphviz DOT format. Options: all (graph every listed package), reverse (follow the packages requiring it instead), optional (include optional dependencies), depth=N (follow at most N links)
 -------
 
 
@@ -109,5 +171,11 @@ text =  the default text colour.
 [operation]
 starting_mode = valid options: \"normal\", \"command\", \"search\", or \"info\". Denotes the mode into which pacbrow boots.
 allow_colon_in_search = valid options: true or false. If true, then you will be able to type ':' whilst in search mode, meaning that you can't jump directly to command mode from search mode.
+
+[paths]
+root = the installation root of the system being browsed. Can be overridden with --root.
+dbpath = the pacman database directory. A relative path is resolved against the root. Can be overridden with --dbpath.
+logfile = the pacman log, read for package history. A relative path is resolved against the root. Can be overridden with --logfile.
+cachedir = the pacman package cache, searched for archives when planning a rollback. A relative path is resolved against the root. Can be overridden with --cachedir.
 -------
 ";
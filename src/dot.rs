@@ -0,0 +1,354 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io::{self, Write},
+};
+
+use crate::{
+    depends::find_satisfier,
+    deptree::TreeDirection,
+    package::{dep_name, format_size, Package},
+    provides::ProvidesIndex,
+};
+
+// What to include in a dependency graph
+#[derive(Debug, Copy, Clone)]
+pub struct GraphOptions {
+    pub direction: TreeDirection,
+    // Number of links to follow from the starting packages; None follows every one
+    pub max_depth: Option<usize>,
+    pub optional: bool,
+}
+impl Default for GraphOptions {
+    fn default() -> Self {
+        Self {
+            direction: TreeDirection::Depends,
+            max_depth: None,
+            optional: false,
+        }
+    }
+}
+impl GraphOptions {
+    // Apply a word from the :dot command, e.g. "reverse" or "depth=2". Returns false if the
+    // word isn't an option.
+    pub fn apply(&mut self, word: &str) -> bool {
+        match word {
+            "deps" => self.direction = TreeDirection::Depends,
+            "reverse" => self.direction = TreeDirection::RequiredBy,
+            "optional" => self.optional = true,
+            _ => match word.strip_prefix("depth=").map(str::parse) {
+                Some(Ok(depth)) => self.max_depth = Some(depth),
+                _ => return false,
+            },
+        }
+        true
+    }
+}
+
+// An edge in the graph; edges always point from a package to what it depends on.
+struct Edge<'a> {
+    from: &'a str,
+    to: &'a str,
+    // The dependency as written, if that says more than the target's name
+    label: Option<&'a str>,
+    optional: bool,
+    satisfied: bool,
+}
+
+// Write the dependency graph around `roots` in Graphviz DOT format, resolving dependencies against
+// `packages`. Returns the number of packages in the graph.
+pub fn write_dot(
+    roots: &[&Package],
+    packages: &[Package],
+    options: &GraphOptions,
+    mut writer: impl Write,
+) -> io::Result<usize> {
+    let provides_index = ProvidesIndex::new(packages);
+    let packages_by_name: HashMap<&str, &Package> = packages
+        .iter()
+        .map(|package| (package.name.as_str(), package))
+        .collect();
+
+    writeln!(writer, "digraph dependencies {{")?;
+    writeln!(writer, "    rankdir=LR;")?;
+    writeln!(writer, "    node [shape=box];")?;
+
+    let mut shown: HashSet<&str> = HashSet::new();
+    let mut edges_shown: HashSet<(&str, &str)> = HashSet::new();
+    let mut queue: VecDeque<(&Package, usize)> = VecDeque::new();
+    for root in roots {
+        if shown.insert(&root.name) {
+            writeln!(
+                writer,
+                "    {} [{}, style=bold];",
+                quote(&root.name),
+                node_label(root)
+            )?;
+            queue.push_back((root, 0));
+        }
+    }
+
+    while let Some((package, depth)) = queue.pop_front() {
+        if options
+            .max_depth
+            .is_some_and(|max_depth| depth >= max_depth)
+        {
+            continue;
+        }
+        // (name as written, package it resolves to and whether that satisfies it, optional)
+        let mut links = Vec::new();
+        match options.direction {
+            TreeDirection::Depends => {
+                let optional_deps = package
+                    .optional_deps
+                    .iter()
+                    .filter(|_| options.optional)
+                    .map(|optional_dep| (optional_dep.name.as_str(), true));
+                for (dep, optional) in package
+                    .depends
+                    .iter()
+                    .map(|dep| (dep.as_str(), false))
+                    .chain(optional_deps)
+                {
+                    links.push((dep, find_satisfier(&provides_index, dep), optional));
+                }
+            }
+            TreeDirection::RequiredBy => {
+                let optional_for = package
+                    .optional_for
+                    .iter()
+                    .filter(|_| options.optional)
+                    .map(|name| (name, true));
+                for (name, optional) in package
+                    .required_by
+                    .iter()
+                    .map(|name| (name, false))
+                    .chain(optional_for)
+                {
+                    let requirer = packages_by_name.get(name.as_str()).copied();
+                    links.push((name, requirer.map(|requirer| (requirer, true)), optional));
+                }
+            }
+        }
+
+        for (name, linked, optional) in links {
+            let target = match linked {
+                Some((linked, _)) => linked.name.as_str(),
+                None => dep_name(name),
+            };
+            let (from, to) = match options.direction {
+                TreeDirection::Depends => (package.name.as_str(), target),
+                TreeDirection::RequiredBy => (target, package.name.as_str()),
+            };
+            if !edges_shown.insert((from, to)) {
+                continue;
+            }
+            if shown.insert(target) {
+                match linked {
+                    Some((linked, _)) => {
+                        writeln!(writer, "    {} [{}];", quote(target), node_label(linked))?;
+                        queue.push_back((linked, depth + 1));
+                    }
+                    None => writeln!(
+                        writer,
+                        "    {} [label={}, style=dashed, color=red];",
+                        quote(target),
+                        quote(&format!("{}\nnot installed", target))
+                    )?,
+                }
+            }
+            write_edge(
+                &mut writer,
+                &Edge {
+                    from,
+                    to,
+                    label: (name != target).then_some(name),
+                    optional,
+                    satisfied: linked.is_some_and(|(_, satisfied)| satisfied),
+                },
+            )?;
+        }
+    }
+
+    writeln!(writer, "}}")?;
+    writer.flush()?;
+    Ok(shown.len())
+}
+
+fn write_edge(writer: &mut impl Write, edge: &Edge) -> io::Result<()> {
+    let mut attributes: Vec<String> = Vec::new();
+    if let Some(label) = edge.label {
+        attributes.push(format!("label={}", quote(label)));
+    }
+    if edge.optional {
+        attributes.push(String::from("style=dashed"));
+    }
+    if !edge.satisfied {
+        attributes.push(String::from("color=red"));
+    }
+    let attributes = if attributes.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", attributes.join(", "))
+    };
+    writeln!(
+        writer,
+        "    {} -> {}{};",
+        quote(edge.from),
+        quote(edge.to),
+        attributes
+    )
+}
+
+// e.g. label="glibc\n2.39-1\n45.78 MiB"
+fn node_label(package: &Package) -> String {
+    format!(
+        "label={}",
+        quote(&format!(
+            "{}\n{}\n{}",
+            package.name,
+            package.version,
+            format_size(package.installed_size)
+        ))
+    )
+}
+
+// Quote a DOT identifier, escaping quotes and turning newlines into line breaks.
+fn quote(text: &str) -> String {
+    format!(
+        "\"{}\"",
+        text.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{localdb::link_reverse_deps, package::OptionalDep};
+    use pretty_assertions::assert_eq;
+
+    fn package(name: &str, depends: &[&str], provides: &[&str]) -> Package {
+        Package {
+            name: name.to_owned(),
+            version: String::from("1-1"),
+            depends: depends.iter().map(|dep| dep.to_string()).collect(),
+            provides: provides
+                .iter()
+                .map(|provision| provision.to_string())
+                .collect(),
+            ..Package::default()
+        }
+    }
+
+    fn packages() -> Vec<Package> {
+        let mut packages = vec![
+            Package {
+                optional_deps: vec![OptionalDep::parse("viewer: to show reports")],
+                ..package("app", &["lib", "sh>=5", "gone"], &[])
+            },
+            package("lib", &["libc"], &[]),
+            package("libc", &[], &[]),
+            package("bash", &["libc"], &["sh=4"]),
+            package("viewer", &["libc"], &[]),
+        ];
+        link_reverse_deps(&mut packages);
+        packages
+    }
+
+    fn dot(root: &str, options: GraphOptions) -> String {
+        let packages = packages();
+        let roots: Vec<&Package> = packages
+            .iter()
+            .filter(|package| package.name == root)
+            .collect();
+        let mut output: Vec<u8> = Vec::new();
+        write_dot(&roots, &packages, &options, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn draws_dependencies() {
+        assert_eq!(
+            dot("app", GraphOptions::default()),
+            r#"digraph dependencies {
+    rankdir=LR;
+    node [shape=box];
+    "app" [label="app\n1-1\n0.00 B", style=bold];
+    "lib" [label="lib\n1-1\n0.00 B"];
+    "app" -> "lib";
+    "bash" [label="bash\n1-1\n0.00 B"];
+    "app" -> "bash" [label="sh>=5", color=red];
+    "gone" [label="gone\nnot installed", style=dashed, color=red];
+    "app" -> "gone" [color=red];
+    "libc" [label="libc\n1-1\n0.00 B"];
+    "lib" -> "libc";
+    "bash" -> "libc";
+}
+"#
+        );
+    }
+
+    #[test]
+    fn limits_depth() {
+        let options = GraphOptions {
+            max_depth: Some(1),
+            ..GraphOptions::default()
+        };
+        assert!(!dot("app", options).contains("libc"));
+        let options = GraphOptions {
+            max_depth: Some(0),
+            ..GraphOptions::default()
+        };
+        assert!(!dot("app", options).contains("->"));
+    }
+
+    #[test]
+    fn draws_optional_and_reverse_dependencies() {
+        let optional = dot(
+            "app",
+            GraphOptions {
+                optional: true,
+                ..GraphOptions::default()
+            },
+        );
+        assert!(optional.contains("    \"app\" -> \"viewer\" [style=dashed];\n"));
+        assert!(optional.contains("    \"viewer\" -> \"libc\";\n"));
+
+        // Edges still point from a package to what it depends on
+        let reverse = dot(
+            "libc",
+            GraphOptions {
+                direction: TreeDirection::RequiredBy,
+                max_depth: Some(1),
+                optional: false,
+            },
+        );
+        assert_eq!(
+            reverse
+                .lines()
+                .filter(|line| line.contains("->"))
+                .collect::<Vec<_>>(),
+            [
+                "    \"bash\" -> \"libc\";",
+                "    \"lib\" -> \"libc\";",
+                "    \"viewer\" -> \"libc\";",
+            ]
+        );
+        let reverse = dot(
+            "viewer",
+            GraphOptions {
+                direction: TreeDirection::RequiredBy,
+                max_depth: None,
+                optional: true,
+            },
+        );
+        assert!(reverse.contains("    \"app\" -> \"viewer\" [style=dashed];\n"));
+    }
+
+    #[test]
+    fn quotes_identifiers() {
+        assert_eq!(quote("glibc"), "\"glibc\"");
+        assert_eq!(quote("say \"hi\"\nC:\\"), "\"say \\\"hi\\\"\\nC:\\\\\"");
+    }
+}
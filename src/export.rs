@@ -0,0 +1,199 @@
+use serde::Serialize;
+
+use std::{io, path::Path};
+
+use crate::{date::format_timestamp, package::Package};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    Toml,
+}
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "json" => Some(ExportFormat::Json),
+            "csv" => Some(ExportFormat::Csv),
+            "toml" => Some(ExportFormat::Toml),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::from_name(path.extension()?.to_str()?)
+    }
+}
+
+// TOML documents must be tables, so packages go in a [[package]] array.
+#[derive(Serialize)]
+struct TomlExport<'a> {
+    package: &'a [&'a Package],
+}
+
+// CSV has no nested values: lists are joined with "; " and dates are formatted. The file list is
+// left out; it is included in the JSON and TOML exports.
+#[derive(Serialize)]
+struct CsvRecord<'a> {
+    name: &'a str,
+    version: &'a str,
+    description: &'a str,
+    architecture: &'a str,
+    url: &'a str,
+    licenses: String,
+    groups: String,
+    provides: String,
+    depends: String,
+    optional_deps: String,
+    required_by: String,
+    optional_for: String,
+    conflicts: String,
+    replaces: String,
+    installed_size: u64,
+    packager: &'a str,
+    build_date: String,
+    install_date: String,
+    install_reason: String,
+    validated_by: String,
+    installed: bool,
+    repository: &'a str,
+    repo_version: &'a str,
+    // Sizes of the repository version, empty when it isn't known
+    repo_installed_size: Option<u64>,
+    download_size: Option<u64>,
+}
+impl<'a> CsvRecord<'a> {
+    fn new(package: &'a Package) -> Self {
+        let date = |date: Option<i64>| date.map(format_timestamp).unwrap_or_default();
+        Self {
+            name: &package.name,
+            version: &package.version,
+            description: &package.description,
+            architecture: &package.architecture,
+            url: &package.url,
+            licenses: package.licenses.join("; "),
+            groups: package.groups.join("; "),
+            provides: package.provides.join("; "),
+            depends: package.depends.join("; "),
+            optional_deps: package
+                .optional_deps
+                .iter()
+                .map(|optional_dep| optional_dep.to_string())
+                .collect::<Vec<String>>()
+                .join("; "),
+            required_by: package.required_by.join("; "),
+            optional_for: package.optional_for.join("; "),
+            conflicts: package.conflicts.join("; "),
+            replaces: package.replaces.join("; "),
+            installed_size: package.installed_size,
+            packager: &package.packager,
+            build_date: date(package.build_date),
+            install_date: date(package.install_date),
+            install_reason: format!("{:?}", package.install_reason),
+            validated_by: package.validated_by.join("; "),
+            installed: package.installed,
+            repository: package.repository.as_deref().unwrap_or_default(),
+            repo_version: package.repo_version.as_deref().unwrap_or_default(),
+            repo_installed_size: package.repo_installed_size,
+            download_size: package.download_size,
+        }
+    }
+}
+
+pub fn export_packages(
+    packages: &[&Package],
+    format: ExportFormat,
+    mut writer: impl io::Write,
+) -> io::Result<()> {
+    match format {
+        ExportFormat::Json => {
+            // Converting keeps the kind of any I/O error, such as a broken pipe
+            serde_json::to_writer_pretty(&mut writer, packages).map_err(io::Error::from)?;
+            writeln!(writer)?;
+            writer.flush()
+        }
+        ExportFormat::Csv => {
+            let mut csv_writer = csv::Writer::from_writer(writer);
+            for package in packages {
+                csv_writer
+                    .serialize(CsvRecord::new(package))
+                    .map_err(|e| match e.kind() {
+                        csv::ErrorKind::Io(io_error) => io::Error::new(io_error.kind(), e),
+                        _ => io::Error::other(e),
+                    })?;
+            }
+            csv_writer.flush()
+        }
+        ExportFormat::Toml => {
+            let toml_str =
+                toml::to_string(&TomlExport { package: packages }).map_err(io::Error::other)?;
+            writer.write_all(toml_str.as_bytes())?;
+            writer.flush()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_has_every_field() {
+        let package = Package {
+            name: String::from("acl"),
+            version: String::from("2.3.2-1"),
+            files: vec![String::from("/usr/bin/getfacl")],
+            repo_installed_size: Some(340000),
+            download_size: Some(140000),
+            ..Package::default()
+        };
+        let mut csv = Vec::new();
+        export_packages(&[&package], ExportFormat::Csv, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        let header = lines.next().unwrap().split(',').collect::<Vec<&str>>();
+
+        // Every field of Package is a column, except the file list
+        let json = serde_json::to_value(&package).unwrap();
+        let mut fields = json
+            .as_object()
+            .unwrap()
+            .keys()
+            .filter(|field| *field != "files")
+            .map(String::as_str)
+            .collect::<Vec<&str>>();
+        fields.sort();
+        let mut columns = header.clone();
+        columns.sort();
+        assert_eq!(columns, fields);
+
+        let row = lines.next().unwrap().split(',').collect::<Vec<&str>>();
+        let column = |name: &str| row[header.iter().position(|c| *c == name).unwrap()];
+        assert_eq!(column("repo_installed_size"), "340000");
+        assert_eq!(column("download_size"), "140000");
+    }
+
+    // A pipe whose reader has gone away
+    struct ClosedPipe;
+    impl io::Write for ClosedPipe {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn keeps_write_error_kinds() {
+        let package = Package {
+            name: String::from("acl"),
+            ..Package::default()
+        };
+        for format in [ExportFormat::Json, ExportFormat::Csv, ExportFormat::Toml] {
+            let e = export_packages(&[&package], format, ClosedPipe).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::BrokenPipe);
+        }
+    }
+}
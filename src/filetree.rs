@@ -0,0 +1,82 @@
+use std::collections::HashSet;
+
+// One visible line of a package's file tree.
+pub struct FileTreeLine {
+    pub text: String,
+    pub path: String,
+}
+
+// Lay out a package's files as an indented tree. Directories in `folded` hide their contents.
+// If `filter` is non-empty, only matching paths and the directories leading to them are shown.
+pub fn file_tree_lines(
+    files: &[String],
+    folded: &HashSet<String>,
+    filter: &str,
+) -> Vec<FileTreeLine> {
+    let matches: Vec<&String> = files.iter().filter(|path| path.contains(filter)).collect();
+    // Directories are shown when they lead to a match
+    let is_shown = |path: &str| {
+        filter.is_empty()
+            || matches.iter().any(|matched| {
+                *matched == path || (path.ends_with('/') && matched.starts_with(path))
+            })
+    };
+
+    files
+        .iter()
+        .filter(|path| is_shown(path))
+        .filter(|path| !ancestors(path).any(|ancestor| folded.contains(ancestor)))
+        .map(|path| {
+            let is_dir = path.ends_with('/');
+            let trimmed = path.trim_end_matches('/');
+            let depth = trimmed.matches('/').count().saturating_sub(1);
+            let name = &trimmed[trimmed.rfind('/').map_or(0, |index| index + 1)..];
+            let marker = match (is_dir, folded.contains(path.as_str())) {
+                (true, true) => "▸ ",
+                (true, false) => "▾ ",
+                (false, _) => "  ",
+            };
+            FileTreeLine {
+                text: format!(
+                    "{}{}{}{}",
+                    "  ".repeat(depth),
+                    marker,
+                    name,
+                    if is_dir { "/" } else { "" }
+                ),
+                path: path.to_owned(),
+            }
+        })
+        .collect()
+}
+
+// Every directory containing `path`, e.g. "/usr/bin/ls" -> "/usr/bin/", "/usr/".
+fn ancestors(path: &str) -> impl Iterator<Item = &str> {
+    let trimmed = path.trim_end_matches('/');
+    trimmed
+        .match_indices('/')
+        .skip(1)
+        .map(move |(index, _)| &path[..=index])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_shows_matches_and_their_directories() {
+        let files = [
+            "/usr/",
+            "/usr/bin/",
+            "/usr/bin/ls",
+            "/usr/bin/lsblk",
+            "/usr/share/",
+        ]
+        .map(String::from);
+        let paths = file_tree_lines(&files, &HashSet::new(), "lsblk")
+            .into_iter()
+            .map(|line| line.path)
+            .collect::<Vec<String>>();
+        assert_eq!(paths, vec!["/usr/", "/usr/bin/", "/usr/bin/lsblk"]);
+    }
+}
@@ -0,0 +1,213 @@
+use std::{fs, io, path::Path};
+
+use crate::date::{format_timestamp, parse_iso_date};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Action {
+    Installed,
+    Reinstalled,
+    Upgraded,
+    Downgraded,
+    Removed,
+}
+impl Action {
+    fn parse(word: &str) -> Option<Self> {
+        match word {
+            "installed" => Some(Action::Installed),
+            "reinstalled" => Some(Action::Reinstalled),
+            "upgraded" => Some(Action::Upgraded),
+            "downgraded" => Some(Action::Downgraded),
+            "removed" => Some(Action::Removed),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Installed => "installed",
+            Action::Reinstalled => "reinstalled",
+            Action::Upgraded => "upgraded",
+            Action::Downgraded => "downgraded",
+            Action::Removed => "removed",
+        }
+    }
+}
+
+// A package change recorded in pacman.log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEvent {
+    pub timestamp: i64,
+    pub name: String,
+    pub action: Action,
+    // Version before the change; None when the package was installed
+    pub old_version: Option<String>,
+    // Version after the change; None when the package was removed
+    pub new_version: Option<String>,
+}
+impl LogEvent {
+    pub fn timeline_line(&self) -> String {
+        let versions = match (&self.old_version, &self.new_version) {
+            (Some(old), Some(new)) if old != new => format!("{} -> {}", old, new),
+            (_, Some(version)) | (Some(version), None) => version.to_owned(),
+            (None, None) => String::new(),
+        };
+        format!(
+            "{}  {:<12}{}",
+            format_timestamp(self.timestamp),
+            self.action.name(),
+            versions
+        )
+    }
+}
+
+pub fn read_log(path: &Path) -> io::Result<Vec<LogEvent>> {
+    let bytes = fs::read(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("{} ({})", e, path.display())))?;
+    Ok(parse_log(&String::from_utf8_lossy(&bytes)))
+}
+
+// Every package change in the log, in the order it was written. Other lines are skipped.
+pub fn parse_log(log: &str) -> Vec<LogEvent> {
+    log.lines().filter_map(parse_log_line).collect()
+}
+
+// e.g. "[2024-01-18T10:14:12+0100] [ALPM] upgraded glibc (2.38-7 -> 2.39-1)"
+fn parse_log_line(line: &str) -> Option<LogEvent> {
+    let (timestamp, rest) = line.strip_prefix('[')?.split_once(']')?;
+    let timestamp = parse_iso_date(timestamp)?;
+    let rest = rest.trim_start();
+    // Older logs have no [ALPM] tag, and older still tag package changes [PACMAN]
+    let rest = match rest
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once("] "))
+    {
+        Some(("ALPM" | "PACMAN", rest)) => rest,
+        Some(_) => return None,
+        None => rest,
+    };
+
+    let (action, rest) = rest.split_once(' ')?;
+    let action = Action::parse(action)?;
+    let (name, versions) = rest.split_once(" (")?;
+    let versions = versions.strip_suffix(')')?;
+    let (old_version, new_version) = match versions.split_once(" -> ") {
+        Some((old, new)) => (Some(old.to_owned()), Some(new.to_owned())),
+        None if action == Action::Removed => (Some(versions.to_owned()), None),
+        None if action == Action::Reinstalled => {
+            (Some(versions.to_owned()), Some(versions.to_owned()))
+        }
+        None => (None, Some(versions.to_owned())),
+    };
+    Some(LogEvent {
+        timestamp,
+        name: name.to_owned(),
+        action,
+        old_version,
+        new_version,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::date::timestamp_from_parts;
+
+    fn read_fixture() -> Vec<LogEvent> {
+        read_log(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/pacman.log")).unwrap()
+    }
+
+    fn event(
+        timestamp: i64,
+        name: &str,
+        action: Action,
+        old_version: Option<&str>,
+        new_version: Option<&str>,
+    ) -> LogEvent {
+        LogEvent {
+            timestamp,
+            name: name.to_owned(),
+            action,
+            old_version: old_version.map(String::from),
+            new_version: new_version.map(String::from),
+        }
+    }
+
+    #[test]
+    fn parses_every_log_format() {
+        let events = read_fixture();
+        let hour = 3600;
+        assert_eq!(
+            events,
+            vec![
+                // Untagged, before pacman 3.x
+                event(
+                    timestamp_from_parts(2012, 3, 4, 10, 20, 0),
+                    "acl",
+                    Action::Installed,
+                    None,
+                    Some("2.2.51-1"),
+                ),
+                // [PACMAN], before [ALPM] existed
+                event(
+                    timestamp_from_parts(2013, 5, 6, 11, 22, 0),
+                    "acl",
+                    Action::Upgraded,
+                    Some("2.2.51-1"),
+                    Some("2.2.52-1"),
+                ),
+                event(
+                    timestamp_from_parts(2024, 1, 18, 10, 14, 13) - hour,
+                    "acl",
+                    Action::Upgraded,
+                    Some("2.2.52-1"),
+                    Some("2.3.2-1"),
+                ),
+                event(
+                    timestamp_from_parts(2024, 2, 1, 8, 0, 0) + 5 * hour,
+                    "acl",
+                    Action::Downgraded,
+                    Some("2.3.2-1"),
+                    Some("2.3.1-2"),
+                ),
+                event(
+                    timestamp_from_parts(2024, 2, 2, 8, 0, 0),
+                    "acl",
+                    Action::Reinstalled,
+                    Some("2.3.1-2"),
+                    Some("2.3.1-2"),
+                ),
+                event(
+                    timestamp_from_parts(2024, 3, 1, 9, 0, 0),
+                    "vim",
+                    Action::Installed,
+                    None,
+                    Some("9.1.0-1"),
+                ),
+                event(
+                    timestamp_from_parts(2024, 3, 2, 9, 0, 0),
+                    "acl",
+                    Action::Removed,
+                    Some("2.3.1-2"),
+                    None,
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn timeline_lines_show_version_changes() {
+        let events = read_fixture();
+        assert_eq!(
+            events[2].timeline_line(),
+            "2024-01-18 09:14:13 UTC  upgraded    2.2.52-1 -> 2.3.2-1"
+        );
+        assert_eq!(
+            events[4].timeline_line(),
+            "2024-02-02 08:00:00 UTC  reinstalled 2.3.1-2"
+        );
+        assert_eq!(
+            events[6].timeline_line(),
+            "2024-03-02 09:00:00 UTC  removed     2.3.1-2"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use std::{
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use crate::{
+    config::Paths,
+    diff::{diff_packages, DiffEntry},
+    package::Package,
+    paclist::{get_package_list, read_snapshot_list, ListOptions, PaclistError},
+};
+
+// Packages are sent to the UI in batches of this size while the database is read.
+const BATCH_SIZE: usize = 100;
+
+pub enum LoadEvent {
+    Progress { loaded: usize, total: usize },
+    // Provisional packages, shown while loading continues
+    Packages(Vec<Package>),
+    // The complete list, replacing everything sent before
+    Finished(Vec<Package>),
+    // Every package in the snapshot being browsed, sent before the list finishes
+    Snapshot(Vec<Package>),
+    // Differences between two package sets, when comparing
+    Diff(Vec<DiffEntry>),
+    Failed(PaclistError),
+    // Something that didn't stop the list loading, sent before it finishes
+    Warning(String),
+}
+
+// Load the package list on a worker thread.
+pub fn spawn_loader(options: ListOptions, paths: Paths) -> Receiver<LoadEvent> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let stream = options.streams();
+        let mut batch: Vec<Package> = Vec::new();
+        let mut on_read = |package: &Package, loaded: usize, total: usize| {
+            if stream && options.matches_unlinked(package) {
+                batch.push(package.clone());
+            }
+            if loaded.is_multiple_of(BATCH_SIZE) || loaded == total {
+                if !batch.is_empty() {
+                    let _ = sender.send(LoadEvent::Packages(std::mem::take(&mut batch)));
+                }
+                let _ = sender.send(LoadEvent::Progress { loaded, total });
+            }
+        };
+
+        // The receiver is gone if the user quit early, so send errors are ignored.
+        let mut warnings: Vec<String> = Vec::new();
+        let result = match &options.input {
+            Some(input) => {
+                read_snapshot_list(&options, input, &mut warnings).map(|(packages, snapshot)| {
+                    let _ = sender.send(LoadEvent::Snapshot(snapshot));
+                    packages
+                })
+            }
+            None => get_package_list(&options, &paths, &mut on_read, &mut warnings),
+        };
+        for warning in warnings {
+            let _ = sender.send(LoadEvent::Warning(warning));
+        }
+        let event = match result {
+            Ok(packages) => LoadEvent::Finished(packages),
+            Err(e) => LoadEvent::Failed(e),
+        };
+        let _ = sender.send(event);
+    });
+    receiver
+}
+
+// Load two package sets on a worker thread and compare them. A missing `new` input compares the
+// old set against the live system.
+pub fn spawn_diff_loader(
+    options: ListOptions,
+    paths: Paths,
+    old: String,
+    new: Option<String>,
+) -> Receiver<LoadEvent> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut on_read = |_: &Package, loaded: usize, total: usize| {
+            if loaded.is_multiple_of(BATCH_SIZE) || loaded == total {
+                let _ = sender.send(LoadEvent::Progress { loaded, total });
+            }
+        };
+        let mut warnings: Vec<String> = Vec::new();
+        let mut load = |input: Option<String>| {
+            let options = ListOptions {
+                input,
+                ..options.clone()
+            };
+            get_package_list(&options, &paths, &mut on_read, &mut warnings)
+        };
+
+        let result = load(Some(old)).and_then(|old| Ok((old, load(new)?)));
+        for warning in warnings {
+            let _ = sender.send(LoadEvent::Warning(warning));
+        }
+        let event = match result {
+            Ok((old, new)) => LoadEvent::Diff(diff_packages(old, new)),
+            Err(e) => LoadEvent::Failed(e),
+        };
+        let _ = sender.send(event);
+    });
+    receiver
+}
@@ -0,0 +1,355 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    cache::{cache_file, EntryStamp, PackageCache},
+    package::{InstallReason, OptionalDep, Package},
+    provides::ProvidesIndex,
+};
+
+// Read every package in <dbpath>/local. `on_read` is called with each package as it is read,
+// along with the number read so far and the total. Entries unchanged since the last run are
+// taken from the cache.
+pub fn read_local_db(
+    dbpath: &Path,
+    on_read: &mut dyn FnMut(&Package, usize, usize),
+) -> io::Result<Vec<Package>> {
+    read_cached_local_db(dbpath, cache_file(dbpath).as_deref(), on_read)
+}
+
+// Read the local database with the cache kept at `cache_path`, if there is one.
+fn read_cached_local_db(
+    dbpath: &Path,
+    cache_path: Option<&Path>,
+    on_read: &mut dyn FnMut(&Package, usize, usize),
+) -> io::Result<Vec<Package>> {
+    let local_dir = dbpath.join("local");
+    let mut package_dirs: Vec<PathBuf> = Vec::new();
+    let entries = fs::read_dir(&local_dir)
+        .map_err(|e| io::Error::new(e.kind(), format!("{} ({})", e, local_dir.display())))?;
+    for entry in entries {
+        let entry = entry?;
+        // Skip ALPM_DB_VERSION and any other stray files
+        if entry.file_type()?.is_dir() {
+            package_dirs.push(entry.path());
+        }
+    }
+
+    let old_cache = cache_path.map_or_else(PackageCache::new, PackageCache::load);
+    let mut new_cache = PackageCache::new();
+    let mut cache_changed = false;
+    let mut packages: Vec<Package> = Vec::with_capacity(package_dirs.len());
+    for dir in &package_dirs {
+        let dir_name = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let stamp = EntryStamp::read(dir)
+            .map_err(|e| io::Error::new(e.kind(), format!("{} ({})", e, dir.display())))?;
+        let package = match old_cache.get(&dir_name, &stamp) {
+            Some(package) => package.clone(),
+            None => {
+                cache_changed = true;
+                read_package_dir(dir)?
+            }
+        };
+        on_read(&package, packages.len() + 1, package_dirs.len());
+        new_cache.insert(dir_name, stamp, package.clone());
+        packages.push(package);
+    }
+    // The cache is only an optimisation, so failing to write it is not an error.
+    if let Some(cache_path) = cache_path {
+        if cache_changed || new_cache.len() != old_cache.len() {
+            let _ = new_cache.save(cache_path);
+        }
+    }
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    link_reverse_deps(&mut packages);
+    Ok(packages)
+}
+
+fn read_package_dir(dir: &Path) -> io::Result<Package> {
+    let desc = fs::read_to_string(dir.join("desc"))
+        .map_err(|e| io::Error::new(e.kind(), format!("{} ({})", e, dir.display())))?;
+    let mut package = parse_desc(&desc);
+    if package.name.is_empty() || package.version.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("missing %NAME% or %VERSION% ({})", dir.display()),
+        ));
+    }
+    package.installed = true;
+    if let Ok(files) = fs::read_to_string(dir.join("files")) {
+        package.files = parse_files(&files);
+    }
+    Ok(package)
+}
+
+// Split a database entry into its %SECTION% blocks.
+pub fn parse_sections(text: &str) -> Vec<(&str, Vec<&str>)> {
+    let mut sections: Vec<(&str, Vec<&str>)> = Vec::new();
+    let mut in_section = false;
+    for line in text.lines() {
+        if line.len() > 2 && line.starts_with('%') && line.ends_with('%') {
+            sections.push((&line[1..line.len() - 1], Vec::new()));
+            in_section = true;
+        } else if line.is_empty() {
+            in_section = false;
+        } else if in_section {
+            if let Some((_, values)) = sections.last_mut() {
+                values.push(line);
+            }
+        }
+    }
+    sections
+}
+
+pub fn parse_desc(desc: &str) -> Package {
+    let mut package = Package::default();
+    for (section, values) in parse_sections(desc) {
+        let text = values.join(" ");
+        let list = || {
+            values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<String>>()
+        };
+        match section {
+            "NAME" => package.name = text,
+            "VERSION" => package.version = text,
+            "DESC" => package.description = text,
+            "URL" => package.url = text,
+            "ARCH" => package.architecture = text,
+            "BUILDDATE" => package.build_date = text.parse().ok(),
+            "INSTALLDATE" => package.install_date = text.parse().ok(),
+            "PACKAGER" => package.packager = text,
+            "SIZE" | "ISIZE" => package.installed_size = text.parse().unwrap_or(0),
+            "CSIZE" => package.download_size = text.parse().ok(),
+            "REASON" => {
+                package.install_reason = if text == "1" {
+                    InstallReason::Dependency
+                } else {
+                    InstallReason::Explicit
+                }
+            }
+            "LICENSE" => package.licenses = list(),
+            "GROUPS" => package.groups = list(),
+            "VALIDATION" => {
+                package.validated_by = values
+                    .iter()
+                    .filter_map(|v| match *v {
+                        "md5" => Some(String::from("MD5 Sum")),
+                        "sha256" => Some(String::from("SHA-256 Sum")),
+                        "pgp" => Some(String::from("Signature")),
+                        _ => None,
+                    })
+                    .collect()
+            }
+            "REPLACES" => package.replaces = list(),
+            "DEPENDS" => package.depends = list(),
+            "OPTDEPENDS" => {
+                package.optional_deps = values.iter().map(|v| OptionalDep::parse(v)).collect()
+            }
+            "CONFLICTS" => package.conflicts = list(),
+            "PROVIDES" => package.provides = list(),
+            _ => {}
+        }
+    }
+    package
+}
+
+// File paths are stored relative to the root; store them the way pacman -Ql prints them.
+pub fn parse_files(files: &str) -> Vec<String> {
+    parse_sections(files)
+        .into_iter()
+        .filter(|(section, _)| *section == "FILES")
+        .flat_map(|(_, values)| values)
+        .map(|path| format!("/{}", path))
+        .collect()
+}
+
+// Fill in Required By and Optional For, which pacman computes rather than stores.
+pub fn link_reverse_deps(packages: &mut [Package]) {
+    let provides_index = ProvidesIndex::new(packages);
+    let mut required_by: Vec<Vec<String>> = vec![Vec::new(); packages.len()];
+    let mut optional_for: Vec<Vec<String>> = vec![Vec::new(); packages.len()];
+    for package in packages.iter() {
+        for dep in &package.depends {
+            for index in provides_index.satisfier_indices(dep) {
+                required_by[*index].push(package.name.to_owned());
+            }
+        }
+        for optional_dep in &package.optional_deps {
+            for index in provides_index.satisfier_indices(&optional_dep.name) {
+                optional_for[*index].push(package.name.to_owned());
+            }
+        }
+    }
+
+    for ((package, mut required_by), mut optional_for) in
+        packages.iter_mut().zip(required_by).zip(optional_for)
+    {
+        required_by.sort();
+        required_by.dedup();
+        optional_for.sort();
+        optional_for.dedup();
+        package.required_by = required_by;
+        package.optional_for = optional_for;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn fixture_dir(entry: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/local")
+            .join(entry)
+    }
+
+    fn read_fixture(entry: &str) -> Package {
+        read_package_dir(&fixture_dir(entry)).unwrap()
+    }
+
+    #[test]
+    fn parses_desc() {
+        let acl = read_fixture("acl-2.3.2-1");
+        assert_eq!(acl.name, "acl");
+        assert_eq!(acl.version, "2.3.2-1");
+        assert_eq!(
+            acl.description,
+            "Access control list utilities, libraries and headers"
+        );
+        assert_eq!(acl.url, "https://savannah.nongnu.org/projects/acl");
+        assert_eq!(acl.architecture, "x86_64");
+        assert_eq!(acl.build_date, Some(1705182278));
+        assert_eq!(acl.install_date, Some(1705572852));
+        assert_eq!(acl.packager, "Christian Hesse <eworm@archlinux.org>");
+        assert_eq!(acl.installed_size, 338104);
+        assert_eq!(acl.install_reason, InstallReason::Dependency);
+        assert_eq!(acl.licenses, vec!["LGPL"]);
+        assert_eq!(acl.validated_by, vec!["Signature"]);
+        assert_eq!(acl.depends, vec!["glibc"]);
+        assert_eq!(acl.provides, vec!["xfsacl", "libacl.so=1-64"]);
+        assert!(acl.installed);
+
+        let app = read_fixture("app-1-1");
+        assert_eq!(app.install_reason, InstallReason::Explicit);
+        assert_eq!(app.install_date, None);
+        assert_eq!(app.validated_by, vec!["SHA-256 Sum", "Signature"]);
+        assert_eq!(app.depends, vec!["libacl.so=1-64", "glibc>=2.30"]);
+        assert_eq!(
+            app.optional_deps,
+            vec![OptionalDep {
+                name: String::from("acl"),
+                description: Some(String::from("for the getfacl command")),
+            }]
+        );
+
+        let glibc = read_fixture("glibc-2.39-1");
+        assert_eq!(
+            glibc.licenses,
+            vec!["GPL-2.0-or-later", "LGPL-2.1-or-later"]
+        );
+        assert!(glibc.depends.is_empty());
+    }
+
+    #[test]
+    fn parses_files() {
+        assert_eq!(
+            read_fixture("acl-2.3.2-1").files,
+            vec![
+                "/usr/",
+                "/usr/bin/",
+                "/usr/bin/getfacl",
+                "/usr/bin/setfacl",
+                "/usr/lib/libacl.so.1",
+            ]
+        );
+        // Entries without a files file have no files
+        assert!(read_fixture("glibc-2.39-1").files.is_empty());
+    }
+
+    #[test]
+    fn links_reverse_deps() {
+        let mut packages = vec![
+            read_fixture("acl-2.3.2-1"),
+            read_fixture("app-1-1"),
+            read_fixture("glibc-2.39-1"),
+        ];
+        link_reverse_deps(&mut packages);
+        let [acl, app, glibc] = &packages[..] else {
+            unreachable!();
+        };
+        // app depends on acl through its libacl.so provision
+        assert_eq!(acl.required_by, vec!["app"]);
+        assert_eq!(acl.optional_for, vec!["app"]);
+        assert_eq!(glibc.required_by, vec!["acl", "app"]);
+        assert!(glibc.optional_for.is_empty());
+        assert!(app.required_by.is_empty());
+    }
+
+    #[test]
+    fn rereads_only_changed_entries() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dbpath = temp_dir.path().join("db");
+        let cache_path = temp_dir.path().join("cache/local.json");
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/local");
+        for entry in ["acl-2.3.2-1", "app-1-1", "glibc-2.39-1"] {
+            let dir = dbpath.join("local").join(entry);
+            fs::create_dir_all(&dir).unwrap();
+            for file in fs::read_dir(fixtures.join(entry)).unwrap() {
+                let file = file.unwrap();
+                fs::copy(file.path(), dir.join(file.file_name())).unwrap();
+            }
+        }
+        let read = || read_cached_local_db(&dbpath, Some(&cache_path), &mut |_, _, _| {}).unwrap();
+        let description = |packages: &[Package], name: &str| {
+            packages
+                .iter()
+                .find(|package| package.name == name)
+                .unwrap()
+                .description
+                .to_owned()
+        };
+        let rewrite_desc = |entry: &str, from: &str, to: &str| {
+            let path = dbpath.join("local").join(entry).join("desc");
+            let desc = fs::read_to_string(&path).unwrap();
+            fs::write(&path, desc.replace(from, to)).unwrap();
+            fs::File::options().write(true).open(path).unwrap()
+        };
+
+        assert_eq!(description(&read(), "glibc"), "GNU C Library");
+        assert!(cache_path.exists());
+
+        // A changed desc has a new modification time...
+        let acl = rewrite_desc("acl-2.3.2-1", "utilities", "tools");
+        acl.set_modified(UNIX_EPOCH + Duration::from_secs(1_800_000_000))
+            .unwrap();
+        // ...so an entry whose time is put back is taken from the cache, even though it changed
+        let glibc_desc = dbpath.join("local/glibc-2.39-1/desc");
+        let modified = fs::metadata(&glibc_desc).unwrap().modified().unwrap();
+        let glibc = rewrite_desc("glibc-2.39-1", "GNU C Library", "Changed");
+        glibc.set_modified(modified).unwrap();
+
+        let packages = read();
+        assert_eq!(
+            description(&packages, "acl"),
+            "Access control list tools, libraries and headers"
+        );
+        assert_eq!(description(&packages, "glibc"), "GNU C Library");
+        assert_eq!(packages.len(), 3);
+        // Nothing but the cache itself is left behind
+        assert_eq!(
+            fs::read_dir(cache_path.parent().unwrap()).unwrap().count(),
+            1
+        );
+    }
+}
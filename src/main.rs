@@ -1,7 +1,7 @@
-use clap::{command, Arg, ArgAction};
+use clap::{command, Arg, ArgAction, ArgMatches};
 use cli_clipboard::set_contents;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -12,20 +12,53 @@ use ratatui::{
 
 use std::{
     error::Error,
-    io,
+    fs::File,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    process,
     time::{Duration, Instant},
 };
 
 mod app;
+mod cache;
+mod check;
 mod config;
+mod date;
+mod depends;
+mod deptree;
+mod diff;
 mod display_texts;
+mod dot;
+mod export;
+mod filetree;
+mod history;
+mod loader;
+mod localdb;
+mod orphans;
+mod owns;
+mod package;
 mod paclist;
+mod provides;
+mod removal;
+mod rollback;
+mod snapshot;
+mod syncdb;
 mod ui;
+mod vercmp;
 
 use crate::{
-    app::{App, Location, Mode},
-    config::read_config,
-    paclist::get_package_list,
+    app::{App, InfoView, Location, Mode, SortKey},
+    check::CheckLevel,
+    config::{read_config, Paths},
+    date::parse_iso_date,
+    deptree::TreeDirection,
+    dot::{write_dot, GraphOptions},
+    export::{export_packages, ExportFormat},
+    loader::{spawn_diff_loader, spawn_loader},
+    owns::find_owners,
+    package::Package,
+    paclist::{get_package_list, read_snapshot_list, ListOptions, PaclistError},
+    rollback::plan_rollback,
     ui::ui,
 };
 
@@ -34,7 +67,6 @@ const TICK_RATE_MS: u64 = 250;
 // Planned features
 // TODO non-latin characters
 // TODO list number of results and index of current result
-// TODO sort by size, date installed, etc.
 // TODO search by fields
 // TODO list number of packages
 fn main() -> Result<(), Box<dyn Error>> {
@@ -81,20 +113,234 @@ fn main() -> Result<(), Box<dyn Error>> {
                 .short('u')
                 .long("upgrades")
                 .action(ArgAction::SetTrue)
-                .help("Filter to packages that are out-of-date on the current system."),
+                .help("Filter to packages with a newer version in the sync database(s), showing the installed and available versions and the total download and installed size change."),
+        )
+        .arg(
+            Arg::new("sync")
+                .short('S')
+                .long("sync")
+                .action(ArgAction::SetTrue)
+                .help("List every package in the sync database(s), marking those already installed."),
+        )
+        .arg(
+            Arg::new("available")
+                .short('a')
+                .long("available")
+                .action(ArgAction::SetTrue)
+                .help("List packages in the sync database(s) that are not installed."),
+        )
+        .arg(
+            Arg::new("orphans")
+                .long("orphans")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["sync", "available", "upgrades"])
+                .help("List every dependency that could be removed: packages not required by anything, plus packages only required by those, and so on. Shows the space they take and why each is orphaned."),
+        )
+        .arg(
+            Arg::new("broken")
+                .long("broken")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["sync", "available", "upgrades"])
+                .help("List packages with dependencies that no installed package satisfies, checking version constraints and provides like pacman -Dk."),
+        )
+        .arg(
+            Arg::new("root")
+                .short('r')
+                .long("root")
+                .value_name("PATH")
+                .help("Browse the system installed under PATH instead of the configured root."),
+        )
+        .arg(
+            Arg::new("dbpath")
+                .short('b')
+                .long("dbpath")
+                .value_name("PATH")
+                .help("Read the pacman database from PATH instead of the configured dbpath."),
+        )
+        .arg(
+            Arg::new("logfile")
+                .long("logfile")
+                .value_name("PATH")
+                .help("Read package history from PATH instead of the configured logfile."),
+        )
+        .arg(
+            Arg::new("cachedir")
+                .long("cachedir")
+                .value_name("PATH")
+                .help("Look for cached package archives in PATH instead of the configured cachedir."),
+        )
+        .arg(
+            Arg::new("input")
+                .short('i')
+                .long("input")
+                .value_name("FILE")
+                .conflicts_with_all(["sync", "available", "upgrades", "root", "dbpath"])
+                .help("Browse a saved snapshot instead of this system's packages. FILE may be a pacman -Qi or -Qil dump, or a JSON or TOML export; use - for standard input."),
+        )
+        .arg(
+            Arg::new("diff")
+                .long("diff")
+                .value_name("FILE")
+                .num_args(1..=2)
+                .conflicts_with_all(["input", "sync", "available", "upgrades", "export"])
+                .help("Compare two package sets, showing packages added, removed, upgraded or downgraded. Given one snapshot FILE, compare it against this system; given two, compare the first against the second."),
+        )
+        .arg(
+            Arg::new("rollback")
+                .long("rollback")
+                .value_name("DATE")
+                .conflicts_with_all(["input", "diff", "export"])
+                .help("Print a pacman -U plan that restores the packages installed at DATE (YYYY-MM-DD or YYYY-MM-DDTHH:MM, in UTC), using pacman.log and the package cache, then exit. The plan is only printed, never run."),
+        )
+        .arg(
+            Arg::new("owns")
+                .long("owns")
+                .value_name("PATH")
+                .num_args(1..)
+                .conflicts_with_all(["diff", "export", "rollback"])
+                .help("Print the package that owns each PATH, like pacman -Qo, then exit."),
+        )
+        .arg(
+            Arg::new("export")
+                .long("export")
+                .value_name("FILE")
+                .help("Export the package list to FILE and exit instead of opening the browser. The format is chosen by FILE's extension (.json, .csv or .toml); use - for standard output."),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .value_parser(["json", "csv", "toml"])
+                .help("Format for --export. Required when exporting to standard output."),
+        )
+        .arg(
+            Arg::new("dot")
+                .long("dot")
+                .value_name("FILE")
+                .conflicts_with_all(["diff", "export", "rollback", "owns"])
+                .help("Write the dependency graph of the listed packages to FILE in Graphviz DOT format and exit; use - for standard output. Each node shows the package's version and installed size."),
+        )
+        .arg(
+            Arg::new("dot-direction")
+                .long("dot-direction")
+                .value_name("DIRECTION")
+                .value_parser(["deps", "reverse"])
+                .requires("dot")
+                .help("Follow dependencies (deps, the default) or the packages requiring them (reverse) in the --dot graph."),
+        )
+        .arg(
+            Arg::new("dot-depth")
+                .long("dot-depth")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .requires("dot")
+                .help("Only follow N links from the listed packages in the --dot graph."),
+        )
+        .arg(
+            Arg::new("dot-optional")
+                .long("dot-optional")
+                .action(ArgAction::SetTrue)
+                .requires("dot")
+                .help("Include optional dependencies in the --dot graph, drawn dashed."),
         )
         .get_matches();
 
     // Load config
-    let config_toml = read_config()?;
+    let mut config_toml = read_config()?;
+    if let Some(root) = cli_match.get_one::<String>("root") {
+        config_toml.paths.root = PathBuf::from(root);
+    }
+    if let Some(dbpath) = cli_match.get_one::<String>("dbpath") {
+        config_toml.paths.dbpath = PathBuf::from(dbpath);
+    }
+    if let Some(logfile) = cli_match.get_one::<String>("logfile") {
+        config_toml.paths.logfile = PathBuf::from(logfile);
+    }
+    if let Some(cachedir) = cli_match.get_one::<String>("cachedir") {
+        config_toml.paths.cachedir = PathBuf::from(cachedir);
+    }
+
+    if let Some(export_path) = cli_match.get_one::<String>("export") {
+        let format = cli_match.get_one::<String>("format").map(String::as_str);
+        if let Err(e) = run_export(&cli_match, &config_toml.paths, export_path, format) {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+        return Ok(());
+    }
 
-    // Get list of packages
-    let package_list = get_package_list(&cli_match)?;
+    if let Some(dot_path) = cli_match.get_one::<String>("dot") {
+        if let Err(e) = run_dot_export(&cli_match, &config_toml.paths, dot_path) {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(paths) = cli_match.get_many::<String>("owns") {
+        let options = ListOptions {
+            input: cli_match.get_one::<String>("input").cloned(),
+            ..ListOptions::default()
+        };
+        let packages = match read_package_list(&options, &config_toml.paths) {
+            Ok(packages) => packages,
+            Err(e) => {
+                eprintln!("{e}");
+                process::exit(1);
+            }
+        };
+        let mut all_owned = true;
+        for path in paths {
+            match find_owners(&packages, path, &config_toml.paths.root) {
+                Some((file, owners)) => {
+                    for owner in owners {
+                        println!("{} is owned by {} {}", file, owner.name, owner.version);
+                    }
+                }
+                None => {
+                    eprintln!("No package owns {path}");
+                    all_owned = false;
+                }
+            }
+        }
+        process::exit(if all_owned { 0 } else { 1 });
+    }
 
-    if package_list.is_empty() {
-        eprintln!("Unable to get package list. Please ensure that you are using pacman as your package manager, it is working properly, and you have not entered conflicting arguments.");
+    if let Some(date) = cli_match.get_one::<String>("rollback") {
+        let Some(timestamp) = parse_iso_date(date) else {
+            eprintln!("Invalid date \"{date}\". Use YYYY-MM-DD or YYYY-MM-DDTHH:MM.");
+            process::exit(1);
+        };
+        match plan_rollback(&config_toml.paths, timestamp) {
+            Ok(plan) => print!("{}", plan.script()),
+            Err(e) => {
+                eprintln!("Unable to plan rollback: {e}");
+                process::exit(1);
+            }
+        }
+        return Ok(());
     }
 
+    // Start loading the list of packages in the background
+    let list_options = ListOptions::from_cli(&cli_match);
+    let snapshot = list_options.input.clone();
+    let upgrades = list_options.upgrades;
+    let orphans = list_options.orphans;
+    let broken = list_options.broken;
+    let diff_sources = cli_match.get_many::<String>("diff").map(|files| {
+        let mut files = files.cloned();
+        (files.next().unwrap(), files.next())
+    });
+    let loader = match &diff_sources {
+        Some((old, new)) => spawn_diff_loader(
+            list_options,
+            config_toml.paths.clone(),
+            old.to_owned(),
+            new.to_owned(),
+        ),
+        None => spawn_loader(list_options, config_toml.paths.clone()),
+    };
+
     // Terminal setup
     enable_raw_mode()?;
     let mut stderr = io::stderr();
@@ -103,7 +349,17 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut terminal = Terminal::new(backend)?;
 
     // Create app & run it
-    let mut app = App::new(config_toml, package_list);
+    let mut app = App::new(config_toml, loader);
+    app.snapshot = snapshot;
+    app.upgrades = upgrades;
+    app.orphans = orphans;
+    app.broken = broken;
+    app.diff_sources = diff_sources.as_ref().map(|(old, new)| {
+        (
+            old.to_owned(),
+            new.to_owned().unwrap_or(String::from("system")),
+        )
+    });
     let res = run_app(&mut terminal, &mut app, Duration::from_millis(TICK_RATE_MS));
 
     // Restore terminal after app execution complete
@@ -114,6 +370,30 @@ fn main() -> Result<(), Box<dyn Error>> {
         DisableMouseCapture
     )?;
     terminal.show_cursor()?;
+
+    // Quitting before the list finished loading says nothing about the filters
+    if app.package_count() == 0 && app.is_loading() {
+        return Ok(());
+    }
+    // The warnings were shown in the TUI unless it closed because loading stopped
+    if app.load_error.is_some() || app.package_count() == 0 {
+        for warning in &app.load_warnings {
+            eprintln!("{warning}");
+        }
+    }
+    if let Some(e) = app.load_error {
+        eprintln!("{e}");
+        process::exit(1);
+    }
+    if app.package_count() == 0 && diff_sources.is_some() {
+        eprintln!("No differences between the compared package sets.");
+        return Ok(());
+    }
+    if app.package_count() == 0 {
+        eprintln!("No packages match the given filters. Please ensure that you have not entered conflicting arguments.");
+        return Ok(());
+    }
+
     match res {
         Ok(do_print) => {
             if do_print {
@@ -127,6 +407,99 @@ fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+// Read the package list outside the TUI, printing any warnings.
+fn read_package_list(options: &ListOptions, paths: &Paths) -> Result<Vec<Package>, PaclistError> {
+    let mut warnings: Vec<String> = Vec::new();
+    let packages = get_package_list(options, paths, &mut |_, _, _| {}, &mut warnings);
+    for warning in warnings {
+        eprintln!("{warning}");
+    }
+    packages
+}
+
+fn run_export(
+    cli_match: &ArgMatches,
+    paths: &Paths,
+    export_path: &str,
+    format: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let format = match format {
+        Some(name) => ExportFormat::from_name(name),
+        None => ExportFormat::from_path(Path::new(export_path)),
+    }
+    .ok_or("Unknown export format. Use a .json, .csv or .toml file, or pass --format.")?;
+
+    let packages = read_package_list(&ListOptions::from_cli(cli_match), paths)?;
+    let packages = packages.iter().collect::<Vec<_>>();
+    if export_path == "-" {
+        match export_packages(&packages, format, io::stdout().lock()) {
+            // Whatever reads the export stopped early, as with `pacbrow --export - | head`
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+            result => result?,
+        }
+    } else {
+        export_packages(
+            &packages,
+            format,
+            BufWriter::new(File::create(export_path)?),
+        )?;
+    }
+    Ok(())
+}
+
+fn run_dot_export(
+    cli_match: &ArgMatches,
+    paths: &Paths,
+    dot_path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let options = GraphOptions {
+        direction: match cli_match
+            .get_one::<String>("dot-direction")
+            .map(String::as_str)
+        {
+            Some("reverse") => TreeDirection::RequiredBy,
+            _ => TreeDirection::Depends,
+        },
+        max_depth: cli_match.get_one::<usize>("dot-depth").copied(),
+        optional: cli_match.get_flag("dot-optional"),
+    };
+    let list_options = ListOptions::from_cli(cli_match);
+    // Dependencies are resolved against every installed package or every package in the
+    // snapshot, whatever is listed
+    let (roots, packages) = match &list_options.input {
+        Some(input) => {
+            let mut warnings: Vec<String> = Vec::new();
+            let lists = read_snapshot_list(&list_options, input, &mut warnings);
+            for warning in warnings {
+                eprintln!("{warning}");
+            }
+            lists?
+        }
+        None => (
+            read_package_list(&list_options, paths)?,
+            read_package_list(&ListOptions::default(), paths)?,
+        ),
+    };
+    let roots = roots.iter().collect::<Vec<_>>();
+    if dot_path == "-" {
+        match write_dot(&roots, &packages, &options, io::stdout().lock()) {
+            // Whatever reads the graph stopped early
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+            result => {
+                result?;
+            }
+        }
+    } else {
+        write_dot(
+            &roots,
+            &packages,
+            &options,
+            BufWriter::new(File::create(dot_path)?),
+        )?;
+    }
+    Ok(())
+}
+
 fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
@@ -135,6 +508,10 @@ fn run_app<B: Backend>(
     let mut last_tick = Instant::now();
     // Main loop
     loop {
+        if app.poll_loader() {
+            return Ok(false);
+        }
+        app.poll_checker();
         terminal.draw(|f| ui(f, app))?;
 
         let timeout = tick_rate.saturating_sub(last_tick.elapsed());
@@ -149,6 +526,13 @@ fn run_app<B: Backend>(
                         KeyCode::Char(':') => {
                             app.goto_command_mode();
                         }
+                        // Go back or forward through the packages jumped between
+                        KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.jump(true);
+                        }
+                        KeyCode::Tab => {
+                            app.jump(false);
+                        }
                         KeyCode::Char('s') => {
                             app.mode = Mode::Search;
                         }
@@ -158,12 +542,13 @@ fn run_app<B: Backend>(
                         }
                         // Yank selected package name
                         KeyCode::Char('y') => {
-                            set_contents(app.current_paclist[app.list_cursor_index].to_owned())
-                                .unwrap();
+                            if let Some(package) = app.selected_package() {
+                                set_contents(package.name.to_owned()).unwrap();
+                            }
                         }
                         // Yank current package list
                         KeyCode::Char('Y') => {
-                            set_contents(app.current_paclist.join("\n").to_owned()).unwrap();
+                            set_contents(app.displayed_package_names().join("\n")).unwrap();
                         }
                         // Scroll up package list
                         KeyCode::Char('k') | KeyCode::Up => {
@@ -189,6 +574,30 @@ fn run_app<B: Backend>(
                         KeyCode::Char('G') => {
                             app.cursor_jump(&Location::Paclist, app.current_paclist.len() - 1);
                         }
+                        // Switch the info pane between package info and files
+                        KeyCode::Char('f') => {
+                            app.toggle_info_view(InfoView::Files);
+                        }
+                        // Switch the info pane between package info and history
+                        KeyCode::Char('H') => {
+                            app.toggle_info_view(InfoView::History);
+                        }
+                        // Switch the info pane between package info and integrity check results
+                        KeyCode::Char('K') => {
+                            app.toggle_info_view(InfoView::Check);
+                        }
+                        // Switch the info pane between package info and the dependency tree
+                        KeyCode::Char('D') => {
+                            app.toggle_info_view(InfoView::Deps);
+                        }
+                        // Switch the info pane between package info and the reverse dependency tree
+                        KeyCode::Char('R') => {
+                            app.toggle_info_view(InfoView::ReverseDeps);
+                        }
+                        // Switch the info pane between package info and what removing it would do
+                        KeyCode::Char('X') => {
+                            app.toggle_info_view(InfoView::Removal);
+                        }
                         // Enter info mode for the currently selected package
                         KeyCode::Char('l')
                         | KeyCode::Char('i')
@@ -202,6 +611,23 @@ fn run_app<B: Backend>(
                         KeyCode::Char(':') => {
                             app.goto_command_mode();
                         }
+                        KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.jump(true);
+                        }
+                        KeyCode::Tab => {
+                            app.jump(false);
+                        }
+                        // Select the next or previous package named on the current line
+                        KeyCode::Char('w') => {
+                            app.next_info_link(true);
+                        }
+                        KeyCode::Char('b') => {
+                            app.next_info_link(false);
+                        }
+                        // Jump to the selected package
+                        KeyCode::Enter if app.info_view == InfoView::Info => {
+                            app.follow_info_link();
+                        }
                         KeyCode::Char('s') => {
                             app.mode = Mode::Search;
                         }
@@ -211,8 +637,7 @@ fn run_app<B: Backend>(
                         }
                         // Yank current line
                         KeyCode::Char('y') => {
-                            set_contents(app.current_pacinfo[app.info_cursor_index].to_owned())
-                                .unwrap();
+                            set_contents(app.selected_info_line()).unwrap();
                         }
                         // Yank package info
                         KeyCode::Char('Y') => {
@@ -242,6 +667,44 @@ fn run_app<B: Backend>(
                         KeyCode::Char('G') => {
                             app.cursor_jump(&Location::Pacinfo, app.current_pacinfo.len() - 1);
                         }
+                        KeyCode::Char('f') => {
+                            app.toggle_info_view(InfoView::Files);
+                        }
+                        KeyCode::Char('H') => {
+                            app.toggle_info_view(InfoView::History);
+                        }
+                        KeyCode::Char('K') => {
+                            app.toggle_info_view(InfoView::Check);
+                        }
+                        KeyCode::Char('D') => {
+                            app.toggle_info_view(InfoView::Deps);
+                        }
+                        KeyCode::Char('R') => {
+                            app.toggle_info_view(InfoView::ReverseDeps);
+                        }
+                        KeyCode::Char('X') => {
+                            app.toggle_info_view(InfoView::Removal);
+                        }
+                        // Fold or unfold directories in the files view
+                        KeyCode::Char('o') | KeyCode::Enter if app.info_view == InfoView::Files => {
+                            app.toggle_fold();
+                        }
+                        KeyCode::Char('O') if app.info_view == InfoView::Files => {
+                            app.unfold_all();
+                        }
+                        KeyCode::Char('C') if app.info_view == InfoView::Files => {
+                            app.fold_all();
+                        }
+                        // Expand or collapse dependencies in the dependency trees
+                        KeyCode::Char('o') | KeyCode::Enter if app.tree_direction().is_some() => {
+                            app.toggle_dep_expansion();
+                        }
+                        KeyCode::Char('O') if app.tree_direction().is_some() => {
+                            app.expand_all_deps();
+                        }
+                        KeyCode::Char('C') if app.tree_direction().is_some() => {
+                            app.collapse_all_deps();
+                        }
                         _ => {}
                     },
                     Mode::Command => match key.code {
@@ -252,6 +715,127 @@ fn run_app<B: Backend>(
                         }
                         // User submits typed command
                         KeyCode::Enter => match app.current_command.as_str() {
+                            command
+                                if command.starts_with(":export ")
+                                    || command.starts_with(":x ") =>
+                            {
+                                let path = command.split_once(' ').unwrap().1.trim().to_owned();
+                                app.clear(Location::Command);
+                                match app.export_package_list(Path::new(&path)) {
+                                    Ok(count) => app.goto_display_mode(&format!(
+                                        "Exported {} packages to {}",
+                                        count, path
+                                    )),
+                                    Err(e) => app.goto_display_mode(&format!(
+                                        "Unable to export to {}: {}",
+                                        path, e
+                                    )),
+                                }
+                            }
+                            ":files" | ":f" => {
+                                app.clear(Location::Command);
+                                app.set_file_filter("");
+                                app.mode = Mode::Info;
+                            }
+                            command
+                                if command.starts_with(":files ") || command.starts_with(":f ") =>
+                            {
+                                let filter = command.split_once(' ').unwrap().1.trim().to_owned();
+                                app.clear(Location::Command);
+                                app.set_file_filter(&filter);
+                                app.mode = Mode::Info;
+                            }
+                            ":history" => {
+                                app.clear(Location::Command);
+                                if app.info_view != InfoView::History {
+                                    app.toggle_info_view(InfoView::History);
+                                }
+                                app.mode = Mode::Info;
+                            }
+                            ":impact" => {
+                                app.clear(Location::Command);
+                                if app.info_view != InfoView::Removal {
+                                    app.toggle_info_view(InfoView::Removal);
+                                }
+                                app.mode = Mode::Info;
+                            }
+                            command if command.starts_with(":dot ") => {
+                                let mut words = command.split_whitespace().skip(1);
+                                let path = words.next().unwrap_or_default().to_owned();
+                                let mut options = GraphOptions::default();
+                                let mut all = false;
+                                let mut invalid: Option<String> = None;
+                                for word in words {
+                                    if word == "all" {
+                                        all = true;
+                                    } else if !options.apply(word) {
+                                        invalid = Some(word.to_owned());
+                                    }
+                                }
+                                app.clear(Location::Command);
+                                let message = match invalid {
+                                    Some(word) => format!("Unknown :dot option \"{}\"", word),
+                                    None => match app.export_dot(Path::new(&path), all, &options) {
+                                        Ok(count) => format!(
+                                            "Wrote a graph of {} packages to {}",
+                                            count, path
+                                        ),
+                                        Err(e) => format!("Unable to write to {}: {}", path, e),
+                                    },
+                                };
+                                app.goto_display_mode(&message);
+                            }
+                            command if command.starts_with(":provides ") => {
+                                let name = command.split_once(' ').unwrap().1.trim().to_owned();
+                                app.clear(Location::Command);
+                                app.show_providers(&name);
+                            }
+                            command if command.starts_with(":rollback ") => {
+                                let args = command
+                                    .split_whitespace()
+                                    .skip(1)
+                                    .map(str::to_owned)
+                                    .collect::<Vec<String>>();
+                                app.clear(Location::Command);
+                                app.show_rollback(
+                                    args.first().map_or("", String::as_str),
+                                    args.get(1).map(Path::new),
+                                );
+                            }
+                            command if command == ":sort" || command.starts_with(":sort ") => {
+                                let key = command.trim_start_matches(":sort").trim().to_owned();
+                                app.clear(Location::Command);
+                                let (descending, name) = match key.strip_prefix('-') {
+                                    Some(name) => (true, name),
+                                    None => (false, key.as_str()),
+                                };
+                                match SortKey::parse(if name.is_empty() { "name" } else { name }) {
+                                    Some(sort_key) => {
+                                        app.sort_packages(sort_key, descending);
+                                        app.mode = Mode::Normal;
+                                    }
+                                    None => app.goto_display_mode(&format!(
+                                        "Unknown sort key \"{}\". Use name, version, size or date.",
+                                        name
+                                    )),
+                                }
+                            }
+                            command if command.starts_with(":owns ") => {
+                                let path = command.split_once(' ').unwrap().1.trim().to_owned();
+                                app.clear(Location::Command);
+                                app.show_owner(&path);
+                            }
+                            ":qk" | ":qk all" | ":qkk" | ":qkk all" => {
+                                let level = if app.current_command.starts_with(":qkk") {
+                                    CheckLevel::Full
+                                } else {
+                                    CheckLevel::Files
+                                };
+                                let all = app.current_command.ends_with(" all");
+                                app.clear(Location::Command);
+                                app.start_check(level, all);
+                                app.mode = Mode::Info;
+                            }
                             ":help" | ":h" => {
                                 app.clear(Location::Command);
                                 app.goto_display_mode(display_texts::HELP_TEXT);
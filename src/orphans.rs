@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+
+use crate::package::{InstallReason, Package};
+
+// Find the dependencies that could be removed, repeating pacman -Qdt until nothing new turns up:
+// a dependency is an orphan once everything that requires or optionally requires it is an
+// orphan too.
+pub fn find_orphans(packages: &[Package]) -> HashSet<String> {
+    let mut orphans: HashSet<String> = HashSet::new();
+    loop {
+        let found = packages
+            .iter()
+            .filter(|package| {
+                package.install_reason == InstallReason::Dependency
+                    && !orphans.contains(&package.name)
+                    && requirers(package).all(|name| orphans.contains(name))
+            })
+            .map(|package| package.name.to_owned())
+            .collect::<Vec<String>>();
+        if found.is_empty() {
+            return orphans;
+        }
+        orphans.extend(found);
+    }
+}
+
+// Why an orphan can be removed.
+pub fn orphan_reason(package: &Package) -> String {
+    let mut requirers = requirers(package)
+        .map(String::as_str)
+        .collect::<Vec<&str>>();
+    requirers.sort();
+    requirers.dedup();
+    if requirers.is_empty() {
+        String::from("Not required by any installed package")
+    } else {
+        format!("Only required by other orphans: {}", requirers.join("  "))
+    }
+}
+
+fn requirers(package: &Package) -> impl Iterator<Item = &String> {
+    package.required_by.iter().chain(&package.optional_for)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{localdb::link_reverse_deps, package::OptionalDep};
+
+    fn package(name: &str, reason: InstallReason, depends: &[&str], optional: &[&str]) -> Package {
+        Package {
+            name: name.to_owned(),
+            version: String::from("1-1"),
+            install_reason: reason,
+            depends: depends.iter().map(|dep| dep.to_string()).collect(),
+            optional_deps: optional.iter().map(|dep| OptionalDep::parse(dep)).collect(),
+            ..Package::default()
+        }
+    }
+
+    fn orphans(mut packages: Vec<Package>) -> Vec<String> {
+        link_reverse_deps(&mut packages);
+        let mut orphans = find_orphans(&packages).into_iter().collect::<Vec<String>>();
+        orphans.sort();
+        orphans
+    }
+
+    #[test]
+    fn finds_chains_of_orphans() {
+        use InstallReason::Dependency;
+        // Only a is unrequired, but once it goes, so can b and then c
+        let chain = vec![
+            package("a", Dependency, &["b"], &[]),
+            package("b", Dependency, &["c"], &[]),
+            package("c", Dependency, &[], &[]),
+        ];
+        assert_eq!(orphans(chain.clone()), vec!["a", "b", "c"]);
+
+        let mut kept = chain;
+        kept[0].install_reason = InstallReason::Explicit;
+        assert!(orphans(kept).is_empty());
+
+        // c is still needed by something that stays
+        let shared = vec![
+            package("a", Dependency, &["b"], &[]),
+            package("b", Dependency, &["c"], &[]),
+            package("c", Dependency, &[], &[]),
+            package("d", InstallReason::Explicit, &["c"], &[]),
+        ];
+        assert_eq!(orphans(shared), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn optional_dependencies_keep_packages() {
+        use InstallReason::{Dependency, Explicit};
+        let packages = vec![
+            package("editor", Explicit, &[], &["spell: spell checking"]),
+            package("spell", Dependency, &["dict"], &[]),
+            package("dict", Dependency, &[], &[]),
+        ];
+        assert!(orphans(packages).is_empty());
+
+        // Optionally required only by an orphan
+        let packages = vec![
+            package("plugin", Dependency, &[], &["spell: spell checking"]),
+            package("spell", Dependency, &["dict"], &[]),
+            package("dict", Dependency, &[], &[]),
+        ];
+        assert_eq!(orphans(packages), vec!["dict", "plugin", "spell"]);
+    }
+
+    #[test]
+    fn explains_why_packages_are_orphans() {
+        let mut packages = vec![
+            package("a", InstallReason::Dependency, &["b"], &["b: extras"]),
+            package("b", InstallReason::Dependency, &[], &[]),
+        ];
+        link_reverse_deps(&mut packages);
+        assert_eq!(
+            orphan_reason(&packages[0]),
+            "Not required by any installed package"
+        );
+        assert_eq!(
+            orphan_reason(&packages[1]),
+            "Only required by other orphans: a"
+        );
+    }
+}
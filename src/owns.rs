@@ -0,0 +1,202 @@
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+// More links than this in one path means they form a loop, as with Linux's ELOOP.
+const MAX_LINKS: usize = 40;
+
+use crate::package::Package;
+
+// Packages owning the file at `path`, along with the path as it appears in their file lists.
+// Relative paths are taken from the current directory. Like pacman -Qo, symlinked directories
+// are resolved, and a symlink is owned by whoever owns the link itself, falling back to its
+// target. Paths under `root` are looked up relative to it.
+pub fn find_owners<'a>(
+    packages: &'a [Package],
+    path: &str,
+    root: &Path,
+) -> Option<(String, Vec<&'a Package>)> {
+    for candidate in candidate_paths(path, root) {
+        let dir_candidate = format!("{}/", candidate.trim_end_matches('/'));
+        let owners = packages
+            .iter()
+            .filter(|package| {
+                package
+                    .files
+                    .iter()
+                    .any(|file| *file == candidate || *file == dir_candidate)
+            })
+            .collect::<Vec<&Package>>();
+        if let Some(owner) = owners.first() {
+            // Report directories the way they are listed, with a trailing slash
+            let file = if owner.files.contains(&dir_candidate) {
+                dir_candidate
+            } else {
+                candidate
+            };
+            return Some((file, owners));
+        }
+    }
+    None
+}
+
+fn candidate_paths(path: &str, root: &Path) -> Vec<String> {
+    let absolute = match env::current_dir() {
+        Ok(current_dir) => normalize(&current_dir.join(path)),
+        Err(_) => normalize(Path::new(path)),
+    };
+    // The path as the system installed in `root` sees it
+    let absolute = match absolute.strip_prefix(root) {
+        Ok(relative) => Path::new("/").join(relative),
+        Err(_) => absolute,
+    };
+    let mut candidates = vec![absolute.clone()];
+    if let (Some(parent), Some(file_name)) = (absolute.parent(), absolute.file_name()) {
+        if let Some(parent) = canonicalize_in_root(parent, root) {
+            candidates.push(parent.join(file_name));
+        }
+    }
+    if let Some(target) = canonicalize_in_root(&absolute, root) {
+        candidates.push(target);
+    }
+
+    let mut paths: Vec<String> = Vec::new();
+    for candidate in candidates {
+        let candidate = candidate.to_string_lossy().to_string();
+        if !paths.contains(&candidate) {
+            paths.push(candidate);
+        }
+    }
+    paths
+}
+
+// Resolve every symlink in `path`, an absolute path on the system installed in `root`. Like
+// fs::canonicalize, but absolute link targets are looked up under `root` rather than the host's /.
+// Fails if the path doesn't exist.
+fn canonicalize_in_root(path: &Path, root: &Path) -> Option<PathBuf> {
+    // Components still to resolve, last first; None stands for ".."
+    let components = |path: &Path| {
+        path.components()
+            .rev()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(Some(name.to_owned())),
+                Component::ParentDir => Some(None),
+                _ => None,
+            })
+            .collect::<Vec<Option<OsString>>>()
+    };
+    let mut remaining = components(path);
+    let mut resolved = PathBuf::from("/");
+    let mut links = 0;
+    while let Some(component) = remaining.pop() {
+        let Some(name) = component else {
+            resolved.pop();
+            continue;
+        };
+        resolved.push(name);
+        let on_disk = root.join(resolved.strip_prefix("/").ok()?);
+        if fs::symlink_metadata(&on_disk)
+            .ok()?
+            .file_type()
+            .is_symlink()
+        {
+            links += 1;
+            if links > MAX_LINKS {
+                return None;
+            }
+            let target = fs::read_link(&on_disk).ok()?;
+            resolved.pop();
+            if target.is_absolute() {
+                resolved = PathBuf::from("/");
+            }
+            remaining.extend(components(&target));
+        }
+    }
+    Some(resolved)
+}
+
+// Remove "." and ".." components without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::symlink;
+
+    use super::*;
+
+    fn package(name: &str, files: &[&str]) -> Package {
+        Package {
+            name: name.to_owned(),
+            files: files.iter().map(|file| file.to_string()).collect(),
+            ..Package::default()
+        }
+    }
+
+    #[test]
+    fn finds_owners_under_a_root() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("usr/lib")).unwrap();
+        fs::write(root.join("usr/lib/libfoo.so.1"), "").unwrap();
+        symlink("libfoo.so.1", root.join("usr/lib/libfoo.so")).unwrap();
+        // Absolute targets point inside the root, not at the host's /usr/lib
+        symlink("/usr/lib/libfoo.so.1", root.join("usr/lib/libbar.so")).unwrap();
+        symlink("usr/lib", root.join("lib")).unwrap();
+        symlink("loop", root.join("loop")).unwrap();
+        let packages = [
+            package("filesystem", &["/lib", "/usr/", "/usr/lib/"]),
+            package(
+                "foo",
+                &["/usr/lib/", "/usr/lib/libfoo.so", "/usr/lib/libfoo.so.1"],
+            ),
+        ];
+        let owners = |path: &str| {
+            find_owners(&packages, root.join(path).to_str().unwrap(), root).map(|(file, owners)| {
+                let names = owners
+                    .iter()
+                    .map(|owner| owner.name.as_str())
+                    .collect::<Vec<&str>>();
+                format!("{} {}", file, names.join(" "))
+            })
+        };
+
+        // A symlink is owned by whoever owns the link itself
+        assert_eq!(
+            owners("usr/lib/libfoo.so").unwrap(),
+            "/usr/lib/libfoo.so foo"
+        );
+        assert_eq!(owners("lib").unwrap(), "/lib filesystem");
+        // Otherwise by whoever owns its target
+        assert_eq!(
+            owners("usr/lib/libbar.so").unwrap(),
+            "/usr/lib/libfoo.so.1 foo"
+        );
+        // Symlinked directories are resolved
+        assert_eq!(
+            owners("lib/libfoo.so.1").unwrap(),
+            "/usr/lib/libfoo.so.1 foo"
+        );
+        assert_eq!(owners("usr/lib").unwrap(), "/usr/lib/ filesystem foo");
+        assert_eq!(
+            owners("usr/lib/../lib/").unwrap(),
+            "/usr/lib/ filesystem foo"
+        );
+        assert_eq!(owners("loop"), None);
+        assert_eq!(owners("usr/lib/missing"), None);
+    }
+}
@@ -0,0 +1,424 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    date::format_timestamp,
+    vercmp::{parse_evr, rpmvercmp, vercmp},
+};
+
+use std::cmp::Ordering;
+
+// Info pane fields whose values name other packages
+const LINK_FIELDS: [&str; 7] = [
+    "Provides",
+    "Depends On",
+    "Optional Deps",
+    "Required By",
+    "Optional For",
+    "Conflicts With",
+    "Replaces",
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InstallReason {
+    #[default]
+    Explicit,
+    Dependency,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OptionalDep {
+    pub name: String,
+    pub description: Option<String>,
+}
+impl OptionalDep {
+    // Parse an entry of the form "name: description".
+    pub fn parse(entry: &str) -> Self {
+        match entry.split_once(": ") {
+            Some((name, description)) => Self {
+                name: name.trim().to_owned(),
+                description: Some(description.trim().to_owned()),
+            },
+            None => Self {
+                name: entry.trim().trim_end_matches(':').to_owned(),
+                description: None,
+            },
+        }
+    }
+}
+impl std::fmt::Display for OptionalDep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.description {
+            Some(description) => write!(f, "{}: {}", self.name, description),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+// A package named in an info line, which can be followed in info mode
+pub struct InfoLink {
+    // Byte range of the name in the line
+    pub start: usize,
+    pub end: usize,
+    // Name of the package, without any version constraint
+    pub target: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    pub architecture: String,
+    pub url: String,
+    pub licenses: Vec<String>,
+    pub groups: Vec<String>,
+    pub provides: Vec<String>,
+    pub depends: Vec<String>,
+    pub optional_deps: Vec<OptionalDep>,
+    pub required_by: Vec<String>,
+    pub optional_for: Vec<String>,
+    pub conflicts: Vec<String>,
+    pub replaces: Vec<String>,
+    // In bytes
+    pub installed_size: u64,
+    pub packager: String,
+    pub build_date: Option<i64>,
+    pub install_date: Option<i64>,
+    pub install_reason: InstallReason,
+    pub validated_by: Vec<String>,
+    pub files: Vec<String>,
+    pub installed: bool,
+    // Sync repository providing this package, if any
+    pub repository: Option<String>,
+    pub repo_version: Option<String>,
+    // Installed size of the repository version, in bytes
+    pub repo_installed_size: Option<u64>,
+    // Size of the repository version's archive, in bytes
+    pub download_size: Option<u64>,
+}
+impl Package {
+    // Compare versions with pacman's rules, whatever the package names.
+    pub fn cmp_version(&self, other: &Package) -> Ordering {
+        vercmp(&self.version, &other.version)
+    }
+
+    // How the repository version differs, if it is newer than the installed one.
+    pub fn upgrade(&self) -> Option<VersionChange> {
+        let repo_version = self.repo_version.as_ref()?;
+        if self.installed && vercmp(repo_version, &self.version) == Ordering::Greater {
+            version_change(&self.version, repo_version)
+        } else {
+            None
+        }
+    }
+
+    // Lines displayed in the info pane, laid out like pacman -Qil.
+    // The info pane's lines, with the packages named in each one. Links are found in the lines
+    // they belong to, so their byte ranges always fall on the names.
+    pub fn linked_info_lines(&self) -> (Vec<String>, Vec<Vec<InfoLink>>) {
+        let mut lines: Vec<String> = Vec::new();
+        let mut links: Vec<Vec<InfoLink>> = Vec::new();
+        for (label, values) in self.info_fields() {
+            for (index, value) in values.iter().enumerate() {
+                let line = info_line(if index == 0 { label } else { "" }, value);
+                let mut line_links: Vec<InfoLink> = Vec::new();
+                if LINK_FIELDS.contains(&label) && value != "None" {
+                    // Optional dependencies are one per line, followed by a description
+                    let items = if label == "Optional Deps" {
+                        vec![value.split(": ").next().unwrap_or_default()]
+                    } else {
+                        value.split("  ").collect()
+                    };
+                    let mut start = line.len() - value.len();
+                    for item in items {
+                        line_links.push(InfoLink {
+                            start,
+                            end: start + item.len(),
+                            target: dep_name(item).to_owned(),
+                        });
+                        start += item.len() + "  ".len();
+                    }
+                }
+                lines.push(line);
+                links.push(line_links);
+            }
+        }
+        (lines, links)
+    }
+
+    // Each info pane field with its value, one entry per line.
+    pub fn info_fields(&self) -> Vec<(&'static str, Vec<String>)> {
+        let date = |date: Option<i64>| match date {
+            Some(timestamp) => format_timestamp(timestamp),
+            None => String::from("None"),
+        };
+        let mut fields: Vec<(&'static str, Vec<String>)> = Vec::new();
+        if let Some(repository) = &self.repository {
+            fields.push(("Repository", vec![repository.to_owned()]));
+        }
+        fields.extend([
+            ("Name", vec![self.name.to_owned()]),
+            ("Version", vec![self.version.to_owned()]),
+        ]);
+        if let Some(repo_version) = &self.repo_version {
+            if self.installed && *repo_version != self.version {
+                fields.push(("Repo Version", vec![repo_version.to_owned()]));
+            }
+        }
+        if let Some(upgrade) = self.upgrade() {
+            fields.push(("Upgrade Type", vec![upgrade.name().to_owned()]));
+        }
+        fields.extend([
+            ("Description", vec![self.description.to_owned()]),
+            ("Architecture", vec![self.architecture.to_owned()]),
+            ("URL", vec![self.url.to_owned()]),
+            ("Licenses", vec![join_list(&self.licenses)]),
+            ("Groups", vec![join_list(&self.groups)]),
+            ("Provides", vec![join_list(&self.provides)]),
+            ("Depends On", vec![join_list(&self.depends)]),
+            (
+                "Optional Deps",
+                if self.optional_deps.is_empty() {
+                    vec![String::from("None")]
+                } else {
+                    self.optional_deps
+                        .iter()
+                        .map(|optional_dep| optional_dep.to_string())
+                        .collect()
+                },
+            ),
+            ("Required By", vec![join_list(&self.required_by)]),
+            ("Optional For", vec![join_list(&self.optional_for)]),
+            ("Conflicts With", vec![join_list(&self.conflicts)]),
+            ("Replaces", vec![join_list(&self.replaces)]),
+            ("Installed Size", vec![format_size(self.installed_size)]),
+        ]);
+        if let Some(download_size) = self.download_size {
+            fields.push(("Download Size", vec![format_size(download_size)]));
+        }
+        fields.extend([
+            ("Packager", vec![self.packager.to_owned()]),
+            ("Build Date", vec![date(self.build_date)]),
+            ("Install Date", vec![date(self.install_date)]),
+            (
+                "Install Reason",
+                vec![String::from(match (self.installed, self.install_reason) {
+                    (false, _) => "Not installed",
+                    (true, InstallReason::Explicit) => "Explicitly installed",
+                    (true, InstallReason::Dependency) => {
+                        "Installed as a dependency for another package"
+                    }
+                })],
+            ),
+            ("Validated By", vec![join_list(&self.validated_by)]),
+        ]);
+        fields
+    }
+}
+
+pub fn info_line(label: &str, value: &str) -> String {
+    if label.is_empty() {
+        format!("{:<18}{}", "", value)
+    } else {
+        format!("{:<16}: {}", label, value)
+    }
+}
+
+fn join_list(list: &[String]) -> String {
+    if list.is_empty() {
+        String::from("None")
+    } else {
+        list.join("  ")
+    }
+}
+
+const SIZE_UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+// pacman's French translation counts in octets
+const FRENCH_SIZE_UNITS: [&str; 6] = ["o", "Kio", "Mio", "Gio", "Tio", "Pio"];
+
+pub fn format_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < SIZE_UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.2} {}", size, SIZE_UNITS[unit])
+}
+
+// A signed change in size, e.g. "+1.50 MiB".
+pub fn format_size_delta(bytes: i64) -> String {
+    format!(
+        "{}{}",
+        if bytes < 0 { "-" } else { "+" },
+        format_size(bytes.unsigned_abs())
+    )
+}
+
+// Parse a size as printed by pacman, e.g. "330.18 KiB" or "330,18 Kio".
+pub fn parse_size(size_str: &str) -> Option<u64> {
+    let (number, unit) = size_str.trim().split_once(' ')?;
+    let number = number.replace(',', ".").parse::<f64>().ok()?;
+    let unit = unit.trim();
+    let exponent = SIZE_UNITS
+        .iter()
+        .position(|u| *u == unit)
+        .or_else(|| FRENCH_SIZE_UNITS.iter().position(|u| *u == unit))?;
+    Some((number * 1024_f64.powi(exponent as i32)).round() as u64)
+}
+
+// Strip any version constraint from a dependency or provision, e.g. "python>=3.12" -> "python".
+pub fn dep_name(dep: &str) -> &str {
+    match dep.find(['<', '>', '=']) {
+        Some(index) => &dep[..index],
+        None => dep,
+    }
+}
+
+// The most significant part of the version that changed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VersionChange {
+    Epoch,
+    Major,
+    Minor,
+    Pkgrel,
+}
+impl VersionChange {
+    pub fn name(&self) -> &'static str {
+        match self {
+            VersionChange::Epoch => "epoch",
+            VersionChange::Major => "major",
+            VersionChange::Minor => "minor",
+            VersionChange::Pkgrel => "pkgrel",
+        }
+    }
+}
+
+// Classify the difference between two versions. The major version is everything before the
+// first separator, so "1.2.3" -> "1.3.0" is a minor change and "1.9" -> "2.0" a major one.
+pub fn version_change(old: &str, new: &str) -> Option<VersionChange> {
+    let (old_epoch, old_version, old_release) = parse_evr(old);
+    let (new_epoch, new_version, new_release) = parse_evr(new);
+    let major = |version: &str| {
+        version
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .next()
+            .unwrap_or_default()
+            .to_owned()
+    };
+    if rpmvercmp(old_epoch, new_epoch) != Ordering::Equal {
+        Some(VersionChange::Epoch)
+    } else if rpmvercmp(old_version, new_version) != Ordering::Equal {
+        if rpmvercmp(&major(old_version), &major(new_version)) != Ordering::Equal {
+            Some(VersionChange::Major)
+        } else {
+            Some(VersionChange::Minor)
+        }
+    } else {
+        match (old_release, new_release) {
+            (Some(old_release), Some(new_release))
+                if rpmvercmp(old_release, new_release) != Ordering::Equal =>
+            {
+                Some(VersionChange::Pkgrel)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_version_changes() {
+        assert_eq!(
+            version_change("1.0-1", "1:1.0-1"),
+            Some(VersionChange::Epoch)
+        );
+        assert_eq!(version_change("1.9-1", "2.0-1"), Some(VersionChange::Major));
+        assert_eq!(
+            version_change("1.2.3-1", "1.3.0-1"),
+            Some(VersionChange::Minor)
+        );
+        assert_eq!(
+            version_change("1.2.3-1", "1.2.3-2"),
+            Some(VersionChange::Pkgrel)
+        );
+        assert_eq!(version_change("1.2.3-1", "1.2.3-1"), None);
+        // A missing pkgrel matches any pkgrel
+        assert_eq!(version_change("1.2.3", "1.2.3-2"), None);
+        // Versions that vercmp considers equal
+        assert_eq!(version_change("1.02-1", "1.2-1"), None);
+    }
+
+    #[test]
+    fn finds_upgrades() {
+        let package = Package {
+            name: String::from("acl"),
+            version: String::from("2.3.1-1"),
+            installed: true,
+            repo_version: Some(String::from("2.3.2-1")),
+            ..Package::default()
+        };
+        assert_eq!(package.upgrade(), Some(VersionChange::Minor));
+        let current = Package {
+            repo_version: Some(package.version.to_owned()),
+            ..package.clone()
+        };
+        assert_eq!(current.upgrade(), None);
+        let downgrade = Package {
+            repo_version: Some(String::from("2.3.1-0")),
+            ..package.clone()
+        };
+        assert_eq!(downgrade.upgrade(), None);
+        let uninstalled = Package {
+            installed: false,
+            ..package
+        };
+        assert_eq!(uninstalled.upgrade(), None);
+    }
+
+    #[test]
+    fn links_names_in_info_lines() {
+        let package = Package {
+            name: String::from("vim"),
+            version: String::from("9.1.0-1"),
+            description: String::from("Vi Improved, a highly configurable, improved vi éditeur"),
+            // Names after multi-byte characters still link on character boundaries
+            depends: vec![
+                String::from("vim-runtime=9.1.0-1"),
+                String::from("libœuvre>=1"),
+                String::from("gpm"),
+            ],
+            optional_deps: vec![
+                OptionalDep::parse("python: Python language support — ünïcode"),
+                OptionalDep::parse("lua"),
+            ],
+            required_by: vec![String::from("gvim")],
+            ..Package::default()
+        };
+        let (lines, links) = package.linked_info_lines();
+        assert_eq!(lines.len(), links.len());
+        let linked: Vec<(&str, &str)> = lines
+            .iter()
+            .zip(&links)
+            .flat_map(|(line, links)| {
+                links
+                    .iter()
+                    .map(|link| (&line[link.start..link.end], link.target.as_str()))
+            })
+            .collect();
+        assert_eq!(
+            linked,
+            [
+                ("vim-runtime=9.1.0-1", "vim-runtime"),
+                ("libœuvre>=1", "libœuvre"),
+                ("gpm", "gpm"),
+                ("python", "python"),
+                ("lua", "lua"),
+                ("gvim", "gvim"),
+            ]
+        );
+    }
+}
@@ -1,57 +1,547 @@
 use clap::ArgMatches;
 
-use std::{io, process::Command};
+use std::{collections::HashSet, error::Error, fmt, io};
 
 use regex::Regex;
 
-use crate::app::Package;
+use crate::{
+    config::Paths,
+    date::parse_pacman_date,
+    depends::broken_dependencies,
+    localdb::read_local_db,
+    orphans::find_orphans,
+    package::{parse_size, InstallReason, OptionalDep, Package},
+    provides::ProvidesIndex,
+    snapshot::read_snapshot,
+    syncdb::{annotate_installed, read_sync_dbs, sync_package_list},
+};
 
-pub fn get_package_list(cli_args: &ArgMatches) -> io::Result<Vec<Package>> {
-    let mut arg_string = String::from("-Qil");
-    if cli_args.get_flag("deps") {
-        arg_string.push('d');
+#[derive(Debug)]
+pub enum PaclistError {
+    Parse(String),
+    Database(io::Error),
+    Snapshot(io::Error),
+}
+impl fmt::Display for PaclistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaclistError::Parse(message) => write!(f, "Unable to parse package list: {}", message),
+            PaclistError::Database(e) => write!(f, "Unable to read pacman database: {}", e),
+            PaclistError::Snapshot(e) => write!(f, "Unable to read snapshot: {}", e),
+        }
     }
-    if cli_args.get_flag("explicit") {
-        arg_string.push('e');
+}
+impl Error for PaclistError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PaclistError::Database(e) | PaclistError::Snapshot(e) => Some(e),
+            _ => None,
+        }
     }
-    if cli_args.get_flag("foreign") {
-        arg_string.push('m');
+}
+impl From<io::Error> for PaclistError {
+    fn from(e: io::Error) -> Self {
+        PaclistError::Database(e)
     }
-    if cli_args.get_flag("native") {
-        arg_string.push('n');
+}
+
+// Which packages to list, as chosen on the command line
+#[derive(Clone, Debug, Default)]
+pub struct ListOptions {
+    pub deps: bool,
+    pub explicit: bool,
+    pub foreign: bool,
+    pub native: bool,
+    pub unrequired: bool,
+    pub upgrades: bool,
+    pub sync: bool,
+    pub available: bool,
+    // Dependencies that could be removed, found by repeating pacman -Qdt
+    pub orphans: bool,
+    // Packages with dependencies nothing installed satisfies, like pacman -Dk
+    pub broken: bool,
+    // Snapshot file to read instead of the live system; "-" is standard input
+    pub input: Option<String>,
+}
+impl ListOptions {
+    pub fn from_cli(cli_args: &ArgMatches) -> Self {
+        Self {
+            deps: cli_args.get_flag("deps"),
+            explicit: cli_args.get_flag("explicit"),
+            foreign: cli_args.get_flag("foreign"),
+            native: cli_args.get_flag("native"),
+            unrequired: cli_args.get_flag("unrequired"),
+            upgrades: cli_args.get_flag("upgrades"),
+            sync: cli_args.get_flag("sync"),
+            available: cli_args.get_flag("available"),
+            orphans: cli_args.get_flag("orphans"),
+            broken: cli_args.get_flag("broken"),
+            input: cli_args.get_one::<String>("input").cloned(),
+        }
     }
-    if cli_args.get_flag("unrequired") {
-        arg_string.push('t');
+
+    // Whether the final list is made of packages read from the local database
+    pub fn lists_installed(&self) -> bool {
+        self.input.is_none()
+            && !self.sync
+            && !self.available
+            && !self.upgrades
+            && !self.orphans
+            && !self.broken
     }
-    if cli_args.get_flag("upgrades") {
-        arg_string.push('u');
+
+    // Whether packages can be shown as they are read. The other filters need dependencies and
+    // repositories, which are only known once everything has been read.
+    pub fn streams(&self) -> bool {
+        self.lists_installed() && !self.foreign && !self.native && !self.unrequired
     }
 
-    let output = Command::new("pacman").arg(arg_string).output()?;
-    let raw_string = match String::from_utf8(output.stdout.to_vec()) {
-        Ok(v) => v,
-        Err(e) => {
-            return Err(io::Error::new(io::ErrorKind::Other, e));
-        }
+    // Filters that can be checked before dependencies and repositories are known
+    pub fn matches_unlinked(&self, package: &Package) -> bool {
+        (!self.deps || package.install_reason == InstallReason::Dependency)
+            && (!self.explicit || package.install_reason == InstallReason::Explicit)
+    }
+
+    pub fn matches(&self, package: &Package) -> bool {
+        self.matches_unlinked(package)
+            && (!self.available || !package.installed)
+            && (!self.upgrades || package.upgrade().is_some())
+            && (!self.foreign || package.repository.is_none())
+            && (!self.native || package.repository.is_some())
+            && (!self.unrequired
+                || package.required_by.is_empty() && package.optional_for.is_empty())
+    }
+}
+
+// `on_read` is called for each installed package as it is read from the local database. Problems
+// that don't stop the list being read, such as an unreadable sync database, go in `warnings`.
+pub fn get_package_list(
+    options: &ListOptions,
+    paths: &Paths,
+    on_read: &mut dyn FnMut(&Package, usize, usize),
+    warnings: &mut Vec<String>,
+) -> Result<Vec<Package>, PaclistError> {
+    if let Some(input) = &options.input {
+        return Ok(filter_packages(options, read_snapshot(input, warnings)?));
+    }
+
+    let mut installed = read_local_db(&paths.dbpath(), on_read)?;
+    let sync_dbs = read_sync_dbs(&paths.root, &paths.dbpath(), warnings)?;
+    annotate_installed(&mut installed, &sync_dbs);
+
+    let packages = if options.sync || options.available {
+        sync_package_list(&installed, &sync_dbs)
+    } else {
+        installed
     };
-    parse_package_list(raw_string)
+    Ok(filter_packages(options, packages))
+}
+
+// Read the snapshot named by `options.input`, returning the packages to list along with every
+// package in it. Standard input can't be read twice, so callers that resolve dependencies against
+// the whole snapshot keep the second list instead of reading it again.
+pub fn read_snapshot_list(
+    options: &ListOptions,
+    input: &str,
+    warnings: &mut Vec<String>,
+) -> Result<(Vec<Package>, Vec<Package>), PaclistError> {
+    let packages = read_snapshot(input, warnings)?;
+    Ok((filter_packages(options, packages.clone()), packages))
 }
 
-fn parse_package_list(raw_string: String) -> io::Result<Vec<Package>> {
-    let split_re = Regex::new(r"(?m)^Name\s+: ").unwrap();
+fn filter_packages(options: &ListOptions, packages: Vec<Package>) -> Vec<Package> {
+    // Orphans and broken dependencies depend on every installed package, so they are found
+    // before anything is filtered
+    let orphans = options.orphans.then(|| find_orphans(&packages));
+    let broken = options.broken.then(|| {
+        let provides_index = ProvidesIndex::new(&packages);
+        packages
+            .iter()
+            .filter(|package| !broken_dependencies(package, &provides_index).is_empty())
+            .map(|package| package.name.to_owned())
+            .collect::<HashSet<String>>()
+    });
+    packages
+        .into_iter()
+        .filter(|package| {
+            orphans
+                .as_ref()
+                .is_none_or(|orphans| orphans.contains(&package.name))
+                && broken
+                    .as_ref()
+                    .is_none_or(|broken| broken.contains(&package.name))
+        })
+        .filter(|package| options.matches(package))
+        .collect()
+}
+
+// Translations of pacman -Qi labels and values, so dumps made under other locales parse too.
+const FIELD_LABELS: &[(&str, &[&str])] = &[
+    ("Name", &["Nom", "Nombre"]),
+    ("Version", &["Versión"]),
+    ("Description", &["Beschreibung", "Descripción"]),
+    ("Architecture", &["Architektur", "Arquitectura"]),
+    ("Licenses", &["Lizenzen", "Licences", "Licencias"]),
+    ("Groups", &["Gruppen", "Groupes", "Grupos"]),
+    ("Provides", &["Stellt bereit", "Fournit", "Provee"]),
+    ("Depends On", &["Hängt ab von", "Dépend de", "Depende de"]),
+    (
+        "Optional Deps",
+        &[
+            "Optionale Abhängigkeiten",
+            "Dépendances opt.",
+            "Dependencias opcionales",
+        ],
+    ),
+    (
+        "Required By",
+        &["Benötigt von", "Requis par", "Requerido por"],
+    ),
+    (
+        "Optional For",
+        &["Optional für", "Optionnel pour", "Opcional para"],
+    ),
+    (
+        "Conflicts With",
+        &["In Konflikt mit", "Est en conflit avec", "En conflicto con"],
+    ),
+    ("Replaces", &["Ersetzt", "Remplace", "Remplaza"]),
+    (
+        "Installed Size",
+        &[
+            "Installationsgröße",
+            "Taille installée",
+            "Tamaño de la instalación",
+        ],
+    ),
+    ("Packager", &["Packer", "Paqueteur", "Empaquetador"]),
+    (
+        "Build Date",
+        &["Erstellt am", "Compilé le", "Fecha de creación"],
+    ),
+    (
+        "Install Date",
+        &["Installiert am", "Installé le", "Fecha de instalación"],
+    ),
+    (
+        "Install Reason",
+        &[
+            "Installationsgrund",
+            "Motif d’installation",
+            "Motif d'installation",
+            "Motivo de la instalación",
+        ],
+    ),
+    (
+        "Validated By",
+        &["Verifiziert durch", "Validé par", "Validado por"],
+    ),
+];
+const EXPLICITLY_INSTALLED: &[&str] = &[
+    "Explicitly installed",
+    "Ausdrücklich installiert",
+    "Explicitement installé",
+    "Instalado explícitamente",
+];
+const INSTALLED_AS_DEPENDENCY: &[&str] = &[
+    "Installed as a dependency for another package",
+    "Installiert als Abhängigkeit eines anderen Paketes",
+    "Installé comme dépendance d’un autre paquet",
+    "Installé comme dépendance d'un autre paquet",
+    "Instalado como dependencia de otro paquete",
+];
+const VALIDATION_VALUES: &[(&str, &[&str])] = &[
+    ("MD5 Sum", &["MD5-Summe", "Somme MD5", "Suma MD5"]),
+    (
+        "SHA-256 Sum",
+        &["SHA-256-Summe", "Somme SHA-256", "Suma SHA-256"],
+    ),
+    ("Signature", &["Signatur", "Firma"]),
+];
+const NONE_VALUES: &[&str] = &[
+    "None", "Nichts", "Aucun", "Aucune", "Nada", "Ninguno", "Ninguna",
+];
+
+fn canonical<'a>(text: &'a str, translations: &[(&'a str, &[&str])]) -> &'a str {
+    translations
+        .iter()
+        .find(|(_, translated)| translated.contains(&text))
+        .map_or(text, |(canonical, _)| canonical)
+}
+
+// Values that can't be understood, such as an unknown install reason, go in `warnings`.
+pub fn parse_package_list(
+    raw_string: String,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<Package>, PaclistError> {
+    let name_labels = FIELD_LABELS[0]
+        .1
+        .iter()
+        .chain([&FIELD_LABELS[0].0])
+        .map(|label| regex::escape(label))
+        .collect::<Vec<String>>()
+        .join("|");
+    let split_re = Regex::new(&format!(r"(?m)^(?:{})\s*: ", name_labels)).unwrap();
+    // Optional dependencies may end with a translated "[installed]" marker.
+    let installed_marker_re = Regex::new(r"\s*\[[^\]]*\]$").unwrap();
 
     let mut package_vec: Vec<Package> = Vec::new();
 
     for raw_entry in split_re.split(&raw_string) {
         let mut lines = raw_entry.lines();
         if let Some(name) = lines.next() {
-            let info = lines.map(|l| l.to_owned()).collect::<Vec<String>>();
-            package_vec.push(Package {
-                name: name.to_string(),
-                info,
-            });
+            package_vec.push(parse_package_entry(
+                name,
+                lines,
+                &installed_marker_re,
+                warnings,
+            )?);
         }
     }
 
     Ok(package_vec)
 }
+
+fn parse_package_entry<'a>(
+    name: &str,
+    lines: impl Iterator<Item = &'a str>,
+    installed_marker_re: &Regex,
+    warnings: &mut Vec<String>,
+) -> Result<Package, PaclistError> {
+    let mut package = Package {
+        name: name.trim().to_owned(),
+        installed: true,
+        ..Package::default()
+    };
+
+    // Each field is a label followed by one or more value lines; continuation lines are indented.
+    let mut fields: Vec<(&str, Vec<&str>)> = Vec::new();
+    let mut in_file_list = false;
+    for line in lines {
+        if in_file_list {
+            if let Some(file) = line
+                .strip_prefix(&package.name)
+                .and_then(|l| l.strip_prefix(' '))
+            {
+                package.files.push(file.to_owned());
+            }
+        } else if line.trim().is_empty() {
+            // Metadata ends at the first blank line; the file list (if any) follows.
+            in_file_list = true;
+        } else if line.starts_with(char::is_whitespace) {
+            if let Some((_, values)) = fields.last_mut() {
+                values.push(line.trim());
+            }
+        } else if let Some((label, value)) = line.split_once(':') {
+            // Labels never contain a colon, so the first one ends the label.
+            fields.push((canonical(label.trim(), FIELD_LABELS), vec![value.trim()]));
+        }
+    }
+
+    for (label, values) in fields {
+        set_field(&mut package, label, &values, installed_marker_re, warnings);
+    }
+    if package.name.is_empty() || package.version.is_empty() {
+        return Err(PaclistError::Parse(format!(
+            "entry \"{}\" has no version",
+            package.name
+        )));
+    }
+    Ok(package)
+}
+
+fn set_field(
+    package: &mut Package,
+    label: &str,
+    values: &[&str],
+    installed_marker_re: &Regex,
+    warnings: &mut Vec<String>,
+) {
+    let text = values.join(" ");
+    // Lists are separated by two spaces, and "None" denotes an empty list.
+    let list = || -> Vec<String> {
+        values
+            .iter()
+            .flat_map(|value| value.split("  "))
+            .map(|item| item.trim())
+            .filter(|item| !item.is_empty() && !NONE_VALUES.contains(item))
+            .map(|item| item.to_owned())
+            .collect()
+    };
+    match label {
+        "Version" => package.version = text,
+        "Description" => package.description = text,
+        "Architecture" => package.architecture = text,
+        "URL" => package.url = text,
+        "Licenses" => package.licenses = list(),
+        "Groups" => package.groups = list(),
+        "Provides" => package.provides = list(),
+        "Depends On" => package.depends = list(),
+        // One optional dependency per line. A description too long for the terminal is wrapped
+        // onto the next line, which unlike a new entry has a space before any colon.
+        "Optional Deps" => {
+            let mut entries: Vec<String> = Vec::new();
+            for value in values {
+                let value = installed_marker_re.replace(value, "");
+                let value = value.trim();
+                let name = value.split_once(':').map_or(value, |(name, _)| name);
+                match entries.last_mut() {
+                    Some(entry) if name.contains(char::is_whitespace) => {
+                        entry.push(' ');
+                        entry.push_str(value);
+                    }
+                    _ if value.is_empty() || NONE_VALUES.contains(&value) => {}
+                    _ => entries.push(value.to_owned()),
+                }
+            }
+            package.optional_deps = entries
+                .iter()
+                .map(|entry| OptionalDep::parse(entry))
+                .collect()
+        }
+        "Required By" => package.required_by = list(),
+        "Optional For" => package.optional_for = list(),
+        "Conflicts With" => package.conflicts = list(),
+        "Replaces" => package.replaces = list(),
+        "Installed Size" => package.installed_size = parse_size(&text).unwrap_or(0),
+        "Packager" => package.packager = text,
+        "Build Date" => package.build_date = parse_pacman_date(&text),
+        "Install Date" => package.install_date = parse_pacman_date(&text),
+        "Install Reason" => {
+            package.install_reason = if EXPLICITLY_INSTALLED.contains(&text.as_str()) {
+                InstallReason::Explicit
+            } else if INSTALLED_AS_DEPENDENCY.contains(&text.as_str()) {
+                InstallReason::Dependency
+            } else {
+                warnings.push(format!(
+                    "Unknown install reason \"{}\" for {}, assuming it was explicitly installed",
+                    text, package.name
+                ));
+                InstallReason::Explicit
+            }
+        }
+        "Validated By" => {
+            package.validated_by = list()
+                .iter()
+                .map(|value| canonical(value, VALIDATION_VALUES).to_owned())
+                .collect()
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::date::timestamp_from_parts;
+
+    fn parse_fixture(locale: &str) -> Vec<Package> {
+        let path = format!(
+            "{}/tests/fixtures/qi/{}.txt",
+            env!("CARGO_MANIFEST_DIR"),
+            locale
+        );
+        parse_package_list(fs::read_to_string(path).unwrap(), &mut Vec::new()).unwrap()
+    }
+
+    #[test]
+    fn parses_english_dump() {
+        let packages = parse_fixture("en");
+        let [acl, vim] = &packages[..] else {
+            panic!("expected two packages, got {}", packages.len());
+        };
+        assert_eq!(acl.installed_size, 338104);
+        assert_eq!(
+            acl.build_date,
+            Some(timestamp_from_parts(2024, 1, 13, 20, 44, 38))
+        );
+        assert_eq!(
+            acl.install_date,
+            Some(timestamp_from_parts(2024, 1, 18, 10, 14, 12))
+        );
+        assert_eq!(acl.install_reason, InstallReason::Dependency);
+        assert_eq!(acl.provides, vec!["xfsacl", "libacl.so=1-64"]);
+        assert!(acl.groups.is_empty() && acl.optional_deps.is_empty());
+
+        assert_eq!(vim.install_reason, InstallReason::Explicit);
+        assert_eq!(
+            vim.build_date,
+            Some(timestamp_from_parts(2023, 12, 1, 23, 0, 0))
+        );
+        assert_eq!(
+            vim.install_date,
+            Some(timestamp_from_parts(2024, 3, 18, 9, 30, 0))
+        );
+        assert_eq!(
+            vim.validated_by,
+            vec!["MD5 Sum", "SHA-256 Sum", "Signature"]
+        );
+        assert_eq!(
+            vim.optional_deps
+                .iter()
+                .map(|optional_dep| optional_dep.to_string())
+                .collect::<Vec<String>>(),
+            vec![
+                "python: Python language support",
+                "ruby: Ruby language support",
+                "lua: Lua language support",
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_translated_dumps_identically() {
+        let english = parse_fixture("en");
+        for locale in ["de", "fr", "es"] {
+            assert_eq!(parse_fixture(locale), english, "{} dump", locale);
+        }
+    }
+
+    #[test]
+    fn parses_wrapped_optional_deps() {
+        let dump = "Name            : vim\n\
+                    Version         : 9.1.0-1\n\
+                    Optional Deps   : python: Python language support, for plugins written\n                  \
+                    in Python [installed]\n                  \
+                    ruby: Ruby language support\n                  \
+                    lua\n";
+        let packages = parse_package_list(dump.to_owned(), &mut Vec::new()).unwrap();
+        assert_eq!(
+            packages[0].optional_deps,
+            vec![
+                OptionalDep::parse(
+                    "python: Python language support, for plugins written in Python"
+                ),
+                OptionalDep::parse("ruby: Ruby language support"),
+                OptionalDep::parse("lua"),
+            ]
+        );
+    }
+
+    #[test]
+    fn warns_about_unknown_install_reason() {
+        let dump = "Name            : acl\n\
+                    Version         : 2.3.2-1\n\
+                    Install Reason  : Installed on a whim\n\
+                    \n\
+                    Name            : vim\n\
+                    Version         : 9.1.0-1\n\
+                    Install Reason  : Installed as a dependency for another package\n";
+        let mut warnings = Vec::new();
+        let packages = parse_package_list(dump.to_owned(), &mut warnings).unwrap();
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].install_reason, InstallReason::Explicit);
+        assert_eq!(packages[1].install_reason, InstallReason::Dependency);
+        assert_eq!(
+            warnings,
+            vec![
+                "Unknown install reason \"Installed on a whim\" for acl, assuming it was \
+                 explicitly installed"
+            ]
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use crate::package::{dep_name, Package};
+
+// Which packages satisfy each dependency name, either by having that name or by providing it, so
+// virtual packages such as "sh" resolve to their providers.
+pub struct ProvidesIndex<'a> {
+    packages: &'a [Package],
+    // Indices into `packages`; a package named after the dependency comes first
+    satisfiers: HashMap<&'a str, Vec<usize>>,
+}
+impl<'a> ProvidesIndex<'a> {
+    pub fn new(packages: &'a [Package]) -> Self {
+        let mut satisfiers: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (index, package) in packages.iter().enumerate() {
+            satisfiers.entry(&package.name).or_default().push(index);
+        }
+        for (index, package) in packages.iter().enumerate() {
+            for provision in &package.provides {
+                let indices = satisfiers.entry(dep_name(provision)).or_default();
+                // Packages sometimes provide their own name
+                if !indices.contains(&index) {
+                    indices.push(index);
+                }
+            }
+        }
+        Self {
+            packages,
+            satisfiers,
+        }
+    }
+
+    // Indices of the packages satisfying `dep`, ignoring any version constraint.
+    pub fn satisfier_indices(&self, dep: &str) -> &[usize] {
+        self.satisfiers
+            .get(dep_name(dep))
+            .map_or(&[], |indices| indices.as_slice())
+    }
+
+    pub fn satisfiers(&self, dep: &str) -> Vec<&'a Package> {
+        self.satisfier_indices(dep)
+            .iter()
+            .map(|index| &self.packages[*index])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, provides: &[&str]) -> Package {
+        Package {
+            name: name.to_owned(),
+            provides: provides
+                .iter()
+                .map(|provision| provision.to_string())
+                .collect(),
+            ..Package::default()
+        }
+    }
+
+    fn names<'a>(index: &ProvidesIndex<'a>, dep: &str) -> Vec<&'a str> {
+        index
+            .satisfiers(dep)
+            .into_iter()
+            .map(|package| package.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn finds_satisfiers() {
+        let packages = [
+            package("dash", &["sh"]),
+            package("bash", &["sh"]),
+            // Listed after its providers, but still found first
+            package("sh", &[]),
+            // Provides its own name as well as a library
+            package("acl", &["acl=2.3.2", "libacl.so=1-64"]),
+        ];
+        let index = ProvidesIndex::new(&packages);
+        assert_eq!(names(&index, "sh"), ["sh", "dash", "bash"]);
+        assert_eq!(names(&index, "acl"), ["acl"]);
+        // Versions are ignored on both sides
+        assert_eq!(names(&index, "libacl.so"), ["acl"]);
+        assert_eq!(names(&index, "libacl.so>=1-64"), ["acl"]);
+        assert_eq!(index.satisfier_indices("sh>3"), [2, 0, 1]);
+        assert!(names(&index, "zsh").is_empty());
+    }
+}
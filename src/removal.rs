@@ -0,0 +1,210 @@
+use std::collections::HashSet;
+
+use crate::{
+    depends::{find_satisfier, Dependency},
+    package::{format_size, InstallReason, Package},
+    provides::ProvidesIndex,
+};
+
+// What removing a package with pacman -Rs would do
+pub struct RemovalImpact<'a> {
+    // The package itself first, then each dependency removed with it, along with the removed
+    // package that pulled it in
+    pub removed: Vec<(&'a Package, Option<&'a str>)>,
+    // Installed packages left with unsatisfied dependencies, and those dependencies
+    pub broken: Vec<(&'a Package, Vec<&'a str>)>,
+}
+impl RemovalImpact<'_> {
+    pub fn freed_size(&self) -> u64 {
+        self.removed
+            .iter()
+            .map(|(package, _)| package.installed_size)
+            .sum()
+    }
+
+    // Lines for the removal impact view
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!(
+                "pacman -Rs {} would remove {}, freeing {}",
+                self.removed[0].0.name,
+                count_packages(self.removed.len()),
+                format_size(self.freed_size())
+            ),
+            String::from(""),
+            String::from("Removed:"),
+        ];
+        for (package, pulled_in_by) in &self.removed {
+            lines.push(format!(
+                "  {} {} ({}){}",
+                package.name,
+                package.version,
+                format_size(package.installed_size),
+                pulled_in_by
+                    .map(|name| format!(", dependency of {}", name))
+                    .unwrap_or_default()
+            ));
+        }
+        lines.push(String::from(""));
+        if self.broken.is_empty() {
+            lines.push(String::from("No installed package would break."));
+        } else {
+            lines.push(format!(
+                "Would break {}:",
+                count_packages(self.broken.len())
+            ));
+            for (package, deps) in &self.broken {
+                lines.push(format!("  {} (needs {})", package.name, deps.join("  ")));
+            }
+        }
+        lines
+    }
+}
+
+fn count_packages(count: usize) -> String {
+    if count == 1 {
+        String::from("1 package")
+    } else {
+        format!("{} packages", count)
+    }
+}
+
+// Work out what pacman -Rs would remove along with `target`: every dependency that wasn't
+// explicitly installed and is only required by packages being removed, repeated until nothing
+// more can go. Also finds the installed packages whose dependencies would no longer be satisfied.
+pub fn removal_impact<'a>(target: &'a Package, packages: &'a [Package]) -> RemovalImpact<'a> {
+    let provides_index = ProvidesIndex::new(packages);
+    let mut removed: Vec<(&Package, Option<&str>)> = vec![(target, None)];
+    let mut removed_names: HashSet<&str> = HashSet::from([target.name.as_str()]);
+    loop {
+        let mut added = false;
+        for index in 0..removed.len() {
+            let package = removed[index].0;
+            for dep in &package.depends {
+                let Some((dependency, true)) = find_satisfier(&provides_index, dep) else {
+                    continue;
+                };
+                if !removed_names.contains(dependency.name.as_str())
+                    && dependency.install_reason == InstallReason::Dependency
+                    && dependency
+                        .required_by
+                        .iter()
+                        .all(|name| removed_names.contains(name.as_str()))
+                {
+                    removed_names.insert(&dependency.name);
+                    removed.push((dependency, Some(&package.name)));
+                    added = true;
+                }
+            }
+        }
+        if !added {
+            break;
+        }
+    }
+
+    // A dependency breaks if only removed packages satisfy it
+    let mut broken: Vec<(&Package, Vec<&str>)> = Vec::new();
+    for package in packages {
+        if removed_names.contains(package.name.as_str()) {
+            continue;
+        }
+        let broken_deps = package
+            .depends
+            .iter()
+            .filter(|dep| {
+                let dependency = Dependency::parse(dep);
+                let satisfiers = provides_index
+                    .satisfiers(dep)
+                    .into_iter()
+                    .filter(|satisfier| dependency.is_satisfied_by(satisfier))
+                    .collect::<Vec<&Package>>();
+                !satisfiers.is_empty()
+                    && satisfiers
+                        .iter()
+                        .all(|satisfier| removed_names.contains(satisfier.name.as_str()))
+            })
+            .map(String::as_str)
+            .collect::<Vec<&str>>();
+        if !broken_deps.is_empty() {
+            broken.push((package, broken_deps));
+        }
+    }
+    RemovalImpact { removed, broken }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::localdb::link_reverse_deps;
+
+    fn package(name: &str, reason: InstallReason, depends: &[&str], provides: &[&str]) -> Package {
+        Package {
+            name: name.to_owned(),
+            version: String::from("1-1"),
+            install_reason: reason,
+            installed_size: 1024,
+            depends: depends.iter().map(|dep| dep.to_string()).collect(),
+            provides: provides
+                .iter()
+                .map(|provision| provision.to_string())
+                .collect(),
+            ..Package::default()
+        }
+    }
+
+    fn packages() -> Vec<Package> {
+        use InstallReason::{Dependency, Explicit};
+        let mut packages = vec![
+            package("app", Explicit, &["lib", "shared", "tool"], &["app-core=1"]),
+            // Only needed by app, and pulls in its own dependency
+            package("lib", Dependency, &["libfoo.so=1"], &[]),
+            package("foo", Dependency, &[], &["libfoo.so=1"]),
+            // Also needed by something that stays
+            package("shared", Dependency, &[], &[]),
+            // Installed explicitly, so pacman -Rs leaves it
+            package("tool", Explicit, &[], &[]),
+            // Only app provides app-core
+            package("plugin", Explicit, &["app-core", "shared"], &[]),
+        ];
+        link_reverse_deps(&mut packages);
+        packages
+    }
+
+    #[test]
+    fn removes_unneeded_dependencies() {
+        let packages = packages();
+        let impact = removal_impact(&packages[0], &packages);
+        let removed = impact
+            .removed
+            .iter()
+            .map(|(package, pulled_in_by)| (package.name.as_str(), *pulled_in_by))
+            .collect::<Vec<(&str, Option<&str>)>>();
+        assert_eq!(
+            removed,
+            vec![("app", None), ("lib", Some("app")), ("foo", Some("lib"))]
+        );
+        assert_eq!(impact.freed_size(), 3 * 1024);
+    }
+
+    #[test]
+    fn finds_packages_that_would_break() {
+        let mut packages = packages();
+        let impact = removal_impact(&packages[0], &packages);
+        let broken = impact
+            .broken
+            .iter()
+            .map(|(package, deps)| (package.name.as_str(), deps.clone()))
+            .collect::<Vec<(&str, Vec<&str>)>>();
+        assert_eq!(broken, vec![("plugin", vec!["app-core"])]);
+
+        // Nothing breaks while another package still provides it
+        packages.push(package(
+            "app-git",
+            InstallReason::Explicit,
+            &[],
+            &["app-core=2"],
+        ));
+        link_reverse_deps(&mut packages);
+        assert!(removal_impact(&packages[0], &packages).broken.is_empty());
+    }
+}
@@ -0,0 +1,401 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::Paths,
+    date::format_timestamp,
+    diff::Change,
+    history::{read_log, LogEvent},
+    localdb::read_local_db,
+    vercmp::vercmp,
+};
+
+// A package that has to change to get back to the package set of an earlier date.
+pub struct RollbackEntry {
+    pub name: String,
+    pub change: Change,
+    pub current_version: Option<String>,
+    // None when the package has to be removed
+    pub target_version: Option<String>,
+    // Cached archive of the target version, if there is one
+    pub archive: Option<PathBuf>,
+}
+impl RollbackEntry {
+    pub fn is_missing(&self) -> bool {
+        self.target_version.is_some() && self.archive.is_none()
+    }
+}
+
+pub struct RollbackPlan {
+    pub timestamp: i64,
+    pub cachedir: PathBuf,
+    pub entries: Vec<RollbackEntry>,
+}
+impl RollbackPlan {
+    pub fn missing_count(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.is_missing())
+            .count()
+    }
+
+    // Lines for the time machine view
+    pub fn view_lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("--TIME MACHINE {}--", format_timestamp(self.timestamp)),
+            format!(
+                "{} packages to change, {} archives missing from {}",
+                self.entries.len(),
+                self.missing_count(),
+                self.cachedir.display()
+            ),
+            String::from(""),
+        ];
+        let name_width = self
+            .entries
+            .iter()
+            .map(|entry| entry.name.len())
+            .max()
+            .unwrap_or(0);
+        for entry in &self.entries {
+            let versions = match (&entry.current_version, &entry.target_version) {
+                (Some(current), Some(target)) => format!("{} -> {}", current, target),
+                (None, Some(target)) => target.to_owned(),
+                (Some(current), None) => current.to_owned(),
+                (None, None) => String::new(),
+            };
+            let source = match (&entry.target_version, &entry.archive) {
+                (None, _) => String::from("remove"),
+                (Some(_), Some(archive)) => archive
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                (Some(_), None) => String::from("MISSING"),
+            };
+            lines.push(format!(
+                "{} {:<name_width$}  {:<30}  {}",
+                entry.change.marker(),
+                entry.name,
+                versions,
+                source
+            ));
+        }
+        if self.entries.is_empty() {
+            lines.push(String::from(
+                "The installed packages already match this date.",
+            ));
+        }
+        lines
+    }
+
+    // A shell script that restores the package set. It is only ever written out for review;
+    // pacbrow never runs it.
+    pub fn script(&self) -> String {
+        let mut script = format!(
+            "#!/bin/sh\n\
+             # Rollback plan generated by pacbrow to restore the packages installed at {}.\n\
+             # Review it before running it.\n",
+            format_timestamp(self.timestamp)
+        );
+        let missing = self
+            .entries
+            .iter()
+            .filter(|entry| entry.is_missing())
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            // A newline in the path would end the comment
+            script.push_str(&format!(
+                "#\n# Not found in {}, so not restored by this plan:\n",
+                self.cachedir.display().to_string().replace('\n', "\\n")
+            ));
+            for entry in missing {
+                script.push_str(&format!(
+                    "#   {} {}\n",
+                    entry.name,
+                    entry.target_version.as_deref().unwrap_or_default()
+                ));
+            }
+        }
+
+        let archives = self
+            .entries
+            .iter()
+            .filter_map(|entry| entry.archive.as_ref())
+            .map(|archive| format!(" \\\n    {}", shell_quote(&archive.to_string_lossy())))
+            .collect::<String>();
+        if !archives.is_empty() {
+            script.push_str(&format!("\npacman -U{}\n", archives));
+        }
+        let removals = self
+            .entries
+            .iter()
+            .filter(|entry| entry.target_version.is_none())
+            .map(|entry| format!(" {}", entry.name))
+            .collect::<String>();
+        if !removals.is_empty() {
+            script.push_str(&format!("\npacman -R{}\n", removals));
+        }
+        script
+    }
+
+    pub fn write_script(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.script())
+    }
+}
+
+// Work out how to return the system to the packages it had installed at `timestamp`, using the
+// local database, pacman.log and the package cache.
+pub fn plan_rollback(paths: &Paths, timestamp: i64) -> io::Result<RollbackPlan> {
+    let installed = read_local_db(&paths.dbpath(), &mut |_, _, _| {})?;
+    let current = installed
+        .into_iter()
+        .map(|package| (package.name, package.version))
+        .collect::<HashMap<String, String>>();
+    let events = read_log(&paths.logfile())?;
+    let cachedir = paths.cachedir();
+    let archives = match read_cache_dir(&cachedir) {
+        Ok(archives) => archives,
+        // Without a cache every archive is simply missing
+        Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+        Err(e) => {
+            return Err(io::Error::new(
+                e.kind(),
+                format!("{} ({})", e, cachedir.display()),
+            ))
+        }
+    };
+
+    let target = versions_at(&events, &current, timestamp);
+    let mut entries: Vec<RollbackEntry> = Vec::new();
+    for (name, target_version) in target {
+        let current_version = current.get(&name).cloned();
+        let change = match (&current_version, &target_version) {
+            (None, None) => continue,
+            (None, Some(_)) => Change::Added,
+            (Some(_), None) => Change::Removed,
+            (Some(current_version), Some(target_version)) => {
+                match vercmp(current_version, target_version) {
+                    Ordering::Equal => continue,
+                    Ordering::Less => Change::Upgraded,
+                    Ordering::Greater => Change::Downgraded,
+                }
+            }
+        };
+        let archive = target_version
+            .as_ref()
+            .and_then(|version| archives.get(&(name.to_owned(), version.to_owned())))
+            .cloned();
+        entries.push(RollbackEntry {
+            name,
+            change,
+            current_version,
+            target_version,
+            archive,
+        });
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(RollbackPlan {
+        timestamp,
+        cachedir,
+        entries,
+    })
+}
+
+// The version of every package known to the log or the local database at `timestamp`, or None if
+// it wasn't installed. A package the log says nothing about is assumed to be unchanged.
+fn versions_at(
+    events: &[LogEvent],
+    current: &HashMap<String, String>,
+    timestamp: i64,
+) -> Vec<(String, Option<String>)> {
+    // Version after the last change at or before the date
+    let mut before: HashMap<&str, Option<String>> = HashMap::new();
+    // Version before the first change after the date, for logs that start after the date
+    let mut after: HashMap<&str, Option<String>> = HashMap::new();
+    for event in events {
+        if event.timestamp <= timestamp {
+            before.insert(&event.name, event.new_version.clone());
+        } else {
+            after
+                .entry(&event.name)
+                .or_insert_with(|| event.old_version.clone());
+        }
+    }
+
+    let names = current
+        .keys()
+        .map(String::as_str)
+        .chain(before.keys().copied())
+        .chain(after.keys().copied())
+        .collect::<BTreeSet<&str>>();
+    names
+        .into_iter()
+        .map(|name| {
+            let version = match (before.get(name), after.get(name)) {
+                (Some(version), _) | (None, Some(version)) => version.clone(),
+                (None, None) => current.get(name).cloned(),
+            };
+            (name.to_owned(), version)
+        })
+        .collect()
+}
+
+// Quote text for a POSIX shell. Inside single quotes only the quote itself needs escaping, by
+// closing the quotes around an escaped one.
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+// Index the archives in the package cache by (name, version).
+fn read_cache_dir(cachedir: &Path) -> io::Result<HashMap<(String, String), PathBuf>> {
+    let mut paths = fs::read_dir(cachedir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<PathBuf>>>()?;
+    // Prefer the same archive every time if a version was cached with several compressions
+    paths.sort();
+
+    let mut archives: HashMap<(String, String), PathBuf> = HashMap::new();
+    for path in paths {
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if let Some(key) = parse_archive_name(file_name) {
+            archives.entry(key).or_insert(path);
+        }
+    }
+    Ok(archives)
+}
+
+// Split e.g. "acl-2.3.2-1-x86_64.pkg.tar.zst" into ("acl", "2.3.2-1"). Signatures are skipped.
+fn parse_archive_name(file_name: &str) -> Option<(String, String)> {
+    if file_name.ends_with(".sig") {
+        return None;
+    }
+    let stem = &file_name[..file_name.find(".pkg.tar")?];
+    let mut parts = stem.rsplitn(4, '-');
+    let _arch = parts.next()?;
+    let pkgrel = parts.next()?;
+    let pkgver = parts.next()?;
+    let name = parts.next()?;
+    Some((name.to_owned(), format!("{}-{}", pkgver, pkgrel)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+    use crate::history::Action;
+
+    fn event(timestamp: i64, name: &str, old: Option<&str>, new: Option<&str>) -> LogEvent {
+        let action = match (old, new) {
+            (None, _) => Action::Installed,
+            (_, None) => Action::Removed,
+            _ => Action::Upgraded,
+        };
+        LogEvent {
+            timestamp,
+            name: name.to_owned(),
+            action,
+            old_version: old.map(String::from),
+            new_version: new.map(String::from),
+        }
+    }
+
+    #[test]
+    fn finds_versions_at_a_date() {
+        let events = [
+            event(100, "acl", None, Some("1-1")),
+            event(200, "acl", Some("1-1"), Some("2-1")),
+            event(300, "acl", Some("2-1"), Some("3-1")),
+            event(150, "gone", None, Some("1-1")),
+            event(250, "gone", Some("1-1"), None),
+            event(250, "new", None, Some("1-1")),
+            // The log starts after the date for this one
+            event(400, "late", Some("4-1"), Some("5-1")),
+        ];
+        let current = [
+            ("acl", "3-1"),
+            ("new", "1-1"),
+            ("late", "5-1"),
+            ("quiet", "7-1"),
+        ]
+        .into_iter()
+        .map(|(name, version)| (name.to_owned(), version.to_owned()))
+        .collect::<HashMap<String, String>>();
+        let versions = |timestamp| {
+            versions_at(&events, &current, timestamp)
+                .into_iter()
+                .map(|(name, version)| format!("{} {}", name, version.unwrap_or_default()))
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(
+            versions(200),
+            vec!["acl 2-1", "gone 1-1", "late 4-1", "new ", "quiet 7-1"]
+        );
+        assert_eq!(
+            versions(50),
+            vec!["acl ", "gone ", "late 4-1", "new ", "quiet 7-1"]
+        );
+        assert_eq!(
+            versions(500),
+            vec!["acl 3-1", "gone ", "late 5-1", "new 1-1", "quiet 7-1"]
+        );
+    }
+
+    #[test]
+    fn parses_archive_names() {
+        let parse = |file_name| parse_archive_name(file_name);
+        assert_eq!(
+            parse("acl-2.3.2-1-x86_64.pkg.tar.zst"),
+            Some((String::from("acl"), String::from("2.3.2-1")))
+        );
+        assert_eq!(
+            parse("python-pip-1:24.0-1-any.pkg.tar.xz"),
+            Some((String::from("python-pip"), String::from("1:24.0-1")))
+        );
+        assert_eq!(parse("acl-2.3.2-1-x86_64.pkg.tar.zst.sig"), None);
+        assert_eq!(parse("acl-2.3.2-1-x86_64.tar.gz"), None);
+        assert_eq!(parse("x86_64.pkg.tar.zst"), None);
+    }
+
+    #[test]
+    fn quotes_archive_paths() {
+        let cachedir = PathBuf::from("/srv/bob's cache\necho injected");
+        let entry = |name: &str, archive: Option<PathBuf>| RollbackEntry {
+            name: name.to_owned(),
+            change: Change::Downgraded,
+            current_version: Some(String::from("2-1")),
+            target_version: Some(String::from("1-1")),
+            archive,
+        };
+        let plan = RollbackPlan {
+            timestamp: 0,
+            cachedir: cachedir.to_owned(),
+            entries: vec![
+                entry("acl", Some(cachedir.join("acl-1-1-x86_64.pkg.tar.zst"))),
+                entry("vim", None),
+            ],
+        };
+        let script = plan.script();
+        assert!(script.contains(
+            "pacman -U \\\n    '/srv/bob'\\''s cache\necho injected/acl-1-1-x86_64.pkg.tar.zst'\n"
+        ));
+        assert!(script.contains("# Not found in /srv/bob's cache\\necho injected, so not restored"));
+        // The shell sees the path as a single argument
+        let output = process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("pacman() {{ printf '%s|' \"$@\"; }}\n{}", script))
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "-U|/srv/bob's cache\necho injected/acl-1-1-x86_64.pkg.tar.zst|"
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use serde::Deserialize;
+
+use std::{
+    fs,
+    io::{self, Read},
+};
+
+use crate::{
+    package::Package,
+    paclist::{parse_package_list, PaclistError},
+};
+
+#[derive(Deserialize)]
+struct TomlSnapshot {
+    package: Vec<Package>,
+}
+
+// Read packages saved from another system: a pacman -Qi or -Qil dump, or a JSON or TOML export.
+// An input of "-" reads standard input. Problems with individual -Qi entries go in `warnings`.
+pub fn read_snapshot(
+    input: &str,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<Package>, PaclistError> {
+    let raw_string = if input == "-" {
+        let mut raw_string = String::new();
+        io::stdin()
+            .read_to_string(&mut raw_string)
+            .map_err(PaclistError::Snapshot)?;
+        raw_string
+    } else {
+        fs::read_to_string(input).map_err(|e| {
+            PaclistError::Snapshot(io::Error::new(e.kind(), format!("{} ({})", e, input)))
+        })?
+    };
+
+    let trimmed = raw_string.trim_start();
+    // An export of no packages is written as "package = []"
+    let mut packages = if trimmed.starts_with("[[package]]") || trimmed.starts_with("package =") {
+        toml::from_str::<TomlSnapshot>(trimmed)
+            .map_err(|e| PaclistError::Parse(e.to_string()))?
+            .package
+    } else if trimmed.starts_with('[') {
+        serde_json::from_str::<Vec<Package>>(trimmed)
+            .map_err(|e| PaclistError::Parse(e.to_string()))?
+    } else {
+        parse_package_list(raw_string, warnings)?
+    };
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(packages)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::export::{export_packages, ExportFormat};
+
+    fn read_file(path: &Path) -> Vec<Package> {
+        read_snapshot(path.to_str().unwrap(), &mut Vec::new()).unwrap()
+    }
+
+    fn qi_packages() -> Vec<Package> {
+        read_file(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/qi/en.txt"))
+    }
+
+    #[test]
+    fn reads_qi_dumps() {
+        let names = qi_packages()
+            .into_iter()
+            .map(|package| package.name)
+            .collect::<Vec<String>>();
+        assert_eq!(names, vec!["acl", "vim"]);
+    }
+
+    #[test]
+    fn reads_back_exports() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let packages = qi_packages();
+        let exported = packages.iter().rev().collect::<Vec<&Package>>();
+        for (format, file) in [
+            (ExportFormat::Json, "packages.json"),
+            (ExportFormat::Toml, "packages.toml"),
+        ] {
+            let path = temp_dir.path().join(file);
+            export_packages(&exported, format, fs::File::create(&path).unwrap()).unwrap();
+            // Sorted by name again, whatever the order in the file
+            assert_eq!(read_file(&path), packages, "{}", file);
+
+            export_packages(&[], format, fs::File::create(&path).unwrap()).unwrap();
+            assert_eq!(read_file(&path), Vec::new(), "empty {}", file);
+        }
+    }
+
+    #[test]
+    fn detects_formats_after_leading_whitespace() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("snapshot");
+        fs::write(&path, "\n  [{\"name\": \"acl\", \"version\": \"2.3.2-1\"").unwrap();
+        // JSON, so a JSON error rather than a -Qi one
+        let Err(PaclistError::Parse(message)) =
+            read_snapshot(path.to_str().unwrap(), &mut Vec::new())
+        else {
+            panic!("expected a parse error");
+        };
+        assert!(message.contains("EOF"), "{}", message);
+
+        fs::write(
+            &path,
+            "\n[[package]]\nname = \"acl\"\nversion = \"2.3.2-1\"\n",
+        )
+        .unwrap();
+        let Err(PaclistError::Parse(message)) =
+            read_snapshot(path.to_str().unwrap(), &mut Vec::new())
+        else {
+            panic!("expected a parse error");
+        };
+        assert!(message.contains("missing field"), "{}", message);
+    }
+}
@@ -0,0 +1,234 @@
+use bzip2::read::BzDecoder;
+use flate2::read::GzDecoder;
+use tar::Archive;
+use xz2::read::XzDecoder;
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Cursor, Read},
+    path::Path,
+};
+
+use crate::{localdb::parse_desc, package::Package};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const XZ_MAGIC: [u8; 6] = [0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const BZIP2_MAGIC: [u8; 3] = [b'B', b'Z', b'h'];
+
+pub struct SyncDb {
+    pub repository: String,
+    pub packages: Vec<Package>,
+}
+
+// Read every <dbpath>/sync/*.db, ordered by repository priority. Repositories are only extra
+// information, so a database that can't be read is skipped, with a message in `warnings`.
+pub fn read_sync_dbs(
+    root: &Path,
+    dbpath: &Path,
+    warnings: &mut Vec<String>,
+) -> io::Result<Vec<SyncDb>> {
+    let mut sync_dbs: Vec<SyncDb> = Vec::new();
+    let sync_dir = dbpath.join("sync");
+    if !sync_dir.is_dir() {
+        return Ok(sync_dbs);
+    }
+
+    for entry in fs::read_dir(&sync_dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "db") {
+            let repository = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let packages = match read_sync_db(&path) {
+                Ok(packages) => packages,
+                Err(e) => {
+                    warnings.push(format!(
+                        "Skipped unreadable sync database {}: {}",
+                        path.display(),
+                        e
+                    ));
+                    continue;
+                }
+            };
+            sync_dbs.push(SyncDb {
+                repository,
+                packages,
+            });
+        }
+    }
+
+    // Repositories listed first in pacman.conf take priority; unlisted ones go last.
+    let repo_order = read_repo_order(root);
+    sync_dbs.sort_by_key(|db| {
+        (
+            repo_order
+                .iter()
+                .position(|repo| *repo == db.repository)
+                .unwrap_or(usize::MAX),
+            db.repository.to_owned(),
+        )
+    });
+    Ok(sync_dbs)
+}
+
+fn read_sync_db(path: &Path) -> io::Result<Vec<Package>> {
+    let bytes = fs::read(path)?;
+    let reader: Box<dyn Read> = if bytes.starts_with(&GZIP_MAGIC) {
+        Box::new(GzDecoder::new(Cursor::new(bytes)))
+    } else if bytes.starts_with(&ZSTD_MAGIC) {
+        Box::new(zstd::Decoder::new(Cursor::new(bytes))?)
+    } else if bytes.starts_with(&XZ_MAGIC) {
+        Box::new(XzDecoder::new(Cursor::new(bytes)))
+    } else if bytes.starts_with(&BZIP2_MAGIC) {
+        Box::new(BzDecoder::new(Cursor::new(bytes)))
+    } else {
+        Box::new(Cursor::new(bytes))
+    };
+
+    let mut packages: Vec<Package> = Vec::new();
+    let mut archive = Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.ends_with("desc") {
+            let mut desc = String::new();
+            entry.read_to_string(&mut desc)?;
+            packages.push(parse_desc(&desc));
+        }
+    }
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(packages)
+}
+
+// Repository section names in the order they appear in pacman.conf.
+fn read_repo_order(root: &Path) -> Vec<String> {
+    fs::read_to_string(root.join("etc/pacman.conf"))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix('[')?.strip_suffix(']'))
+        .filter(|section| *section != "options")
+        .map(|section| section.to_owned())
+        .collect()
+}
+
+// Record which repository each installed package comes from, along with the version available
+// there and its sizes.
+pub fn annotate_installed(packages: &mut [Package], sync_dbs: &[SyncDb]) {
+    let mut repo_packages: HashMap<&str, (&str, &Package)> = HashMap::new();
+    for sync_db in sync_dbs.iter().rev() {
+        for package in &sync_db.packages {
+            repo_packages.insert(&package.name, (&sync_db.repository, package));
+        }
+    }
+    for package in packages.iter_mut() {
+        package.installed = true;
+        if let Some((repository, sync_package)) = repo_packages.get(package.name.as_str()) {
+            package.repository = Some(repository.to_string());
+            set_repo_fields(package, sync_package);
+        }
+    }
+}
+
+fn set_repo_fields(package: &mut Package, sync_package: &Package) {
+    package.repo_version = Some(sync_package.version.to_owned());
+    package.repo_installed_size = Some(sync_package.installed_size);
+    package.download_size = sync_package.download_size;
+}
+
+// Every package in the sync databases. Installed packages use their local entries.
+pub fn sync_package_list(installed: &[Package], sync_dbs: &[SyncDb]) -> Vec<Package> {
+    let installed_indices: HashMap<&str, usize> = installed
+        .iter()
+        .enumerate()
+        .map(|(index, package)| (package.name.as_str(), index))
+        .collect();
+
+    let mut packages: Vec<Package> = Vec::new();
+    for sync_db in sync_dbs {
+        for sync_package in &sync_db.packages {
+            let mut package = match installed_indices.get(sync_package.name.as_str()) {
+                Some(index) => installed[*index].clone(),
+                None => sync_package.clone(),
+            };
+            package.repository = Some(sync_db.repository.to_owned());
+            set_repo_fields(&mut package, sync_package);
+            packages.push(package);
+        }
+    }
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    packages
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use bzip2::write::BzEncoder;
+    use flate2::write::GzEncoder;
+    use tar::{Builder, Header};
+    use xz2::write::XzEncoder;
+
+    use super::*;
+
+    // A sync database tarball holding a single package
+    fn db_tar(name: &str) -> Vec<u8> {
+        let desc = format!("%NAME%\n{}\n\n%VERSION%\n1.0-1\n\n", name);
+        let mut header = Header::new_gnu();
+        header.set_size(desc.len() as u64);
+        header.set_mode(0o644);
+        let mut builder = Builder::new(Vec::new());
+        builder
+            .append_data(&mut header, format!("{}-1.0-1/desc", name), desc.as_bytes())
+            .unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn reads_every_compression_and_skips_unreadable_dbs() {
+        let root = tempfile::tempdir().unwrap();
+        let sync_dir = root.path().join("var/lib/pacman/sync");
+        fs::create_dir_all(&sync_dir).unwrap();
+        let mut gz = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(&db_tar("gzpkg")).unwrap();
+        fs::write(sync_dir.join("gz.db"), gz.finish().unwrap()).unwrap();
+        let mut xz = XzEncoder::new(Vec::new(), 6);
+        xz.write_all(&db_tar("xzpkg")).unwrap();
+        fs::write(sync_dir.join("xz.db"), xz.finish().unwrap()).unwrap();
+        let mut bz = BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bz.write_all(&db_tar("bzpkg")).unwrap();
+        fs::write(sync_dir.join("bz.db"), bz.finish().unwrap()).unwrap();
+        fs::write(
+            sync_dir.join("zst.db"),
+            zstd::encode_all(&db_tar("zstpkg")[..], 0).unwrap(),
+        )
+        .unwrap();
+        fs::write(sync_dir.join("tar.db"), db_tar("tarpkg")).unwrap();
+        fs::write(sync_dir.join("broken.db"), GZIP_MAGIC).unwrap();
+
+        let mut warnings: Vec<String> = Vec::new();
+        let sync_dbs = read_sync_dbs(
+            root.path(),
+            &root.path().join("var/lib/pacman"),
+            &mut warnings,
+        )
+        .unwrap();
+        let names = sync_dbs
+            .iter()
+            .map(|db| (db.repository.as_str(), db.packages[0].name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                ("bz", "bzpkg"),
+                ("gz", "gzpkg"),
+                ("tar", "tarpkg"),
+                ("xz", "xzpkg"),
+                ("zst", "zstpkg"),
+            ]
+        );
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("broken.db"));
+    }
+}
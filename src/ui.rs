@@ -25,6 +25,11 @@ pub fn ui(f: &mut Frame, app: &mut App) {
         })
         .collect::<Vec<Line>>();
 
+    // Underline the package name that Enter would jump to
+    let selected_link = match app.mode {
+        Mode::Info => app.selected_info_link().map(|link| (link.start, link.end)),
+        _ => None,
+    };
     let info_text = (0..app.current_pacinfo.len())
         .map(|index| {
             let mut style = Style::default();
@@ -40,7 +45,15 @@ pub fn ui(f: &mut Frame, app: &mut App) {
                 style = style.fg(app.config.colours.text);
             }
 
-            Line::from(Span::styled(&app.current_pacinfo[index], style))
+            let line = &app.current_pacinfo[index];
+            match selected_link {
+                Some((start, end)) if index == app.info_cursor_index => Line::from(vec![
+                    Span::styled(&line[..start], style),
+                    Span::styled(&line[start..end], style.add_modifier(Modifier::UNDERLINED)),
+                    Span::styled(&line[end..], style),
+                ]),
+                _ => Line::from(Span::styled(line, style)),
+            }
         })
         .collect::<Vec<Line>>();
 
@@ -78,11 +91,11 @@ pub fn ui(f: &mut Frame, app: &mut App) {
         })
         .wrap(Wrap { trim: false })
         .scroll((app.list_cursor_index as u16, 0))
-        .block(Block::default().borders(Borders::ALL).title(format!(
-            "Packages ({}/{})",
-            app.list_cursor_index + 1,
-            app.current_paclist.len()
-        )));
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(app.list_title()),
+        );
     f.render_widget(pac_list, info_layout[0]);
     f.render_stateful_widget(
         Scrollbar::new(ScrollbarOrientation::VerticalLeft)
@@ -108,13 +121,7 @@ pub fn ui(f: &mut Frame, app: &mut App) {
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(match app.mode {
-                    Mode::Display => String::from(""),
-                    _ => match app.selected_package() {
-                        Some(pkg) => pkg.name.to_owned(),
-                        None => String::from(""),
-                    },
-                }),
+                .title(app.info_title()),
         );
     f.render_widget(info, info_layout[1]);
     f.render_stateful_widget(
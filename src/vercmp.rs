@@ -0,0 +1,201 @@
+use std::cmp::Ordering;
+
+// Compare two package versions of the form [epoch:]version[-pkgrel] the way pacman's vercmp does.
+pub fn vercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+    let (epoch_a, version_a, release_a) = parse_evr(a);
+    let (epoch_b, version_b, release_b) = parse_evr(b);
+    rpmvercmp(epoch_a, epoch_b)
+        .then_with(|| rpmvercmp(version_a, version_b))
+        .then_with(|| match (release_a, release_b) {
+            // A missing pkgrel matches any pkgrel
+            (Some(release_a), Some(release_b)) => rpmvercmp(release_a, release_b),
+            _ => Ordering::Equal,
+        })
+}
+
+// Split a version into epoch, version and pkgrel. A missing epoch is "0".
+pub fn parse_evr(evr: &str) -> (&str, &str, Option<&str>) {
+    let digits_end = evr.find(|c: char| !c.is_ascii_digit()).unwrap_or(evr.len());
+    let (epoch, rest) = match evr[digits_end..].strip_prefix(':') {
+        Some(rest) if digits_end == 0 => ("0", rest),
+        Some(rest) => (&evr[..digits_end], rest),
+        None => ("0", evr),
+    };
+    match rest.rfind('-') {
+        Some(index) => (epoch, &rest[..index], Some(&rest[index + 1..])),
+        None => (epoch, rest, None),
+    }
+}
+
+// Compare one part of a version: alternating runs of digits and letters, ignoring other
+// characters except to tell segments apart.
+pub fn rpmvercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+    let a = a.as_bytes();
+    let b = b.as_bytes();
+    let (mut one, mut two) = (0, 0);
+    let (mut ptr1, mut ptr2) = (0, 0);
+
+    while one < a.len() && two < b.len() {
+        while one < a.len() && !a[one].is_ascii_alphanumeric() {
+            one += 1;
+        }
+        while two < b.len() && !b[two].is_ascii_alphanumeric() {
+            two += 1;
+        }
+        if one >= a.len() || two >= b.len() {
+            break;
+        }
+
+        // Separators of different lengths decide the comparison
+        if one - ptr1 != two - ptr2 {
+            return (one - ptr1).cmp(&(two - ptr2));
+        }
+
+        ptr1 = one;
+        ptr2 = two;
+        let is_num = a[ptr1].is_ascii_digit();
+        if is_num {
+            while ptr1 < a.len() && a[ptr1].is_ascii_digit() {
+                ptr1 += 1;
+            }
+            while ptr2 < b.len() && b[ptr2].is_ascii_digit() {
+                ptr2 += 1;
+            }
+        } else {
+            while ptr1 < a.len() && a[ptr1].is_ascii_alphabetic() {
+                ptr1 += 1;
+            }
+            while ptr2 < b.len() && b[ptr2].is_ascii_alphabetic() {
+                ptr2 += 1;
+            }
+        }
+
+        // Segments of different types: numeric segments are newer than alpha ones
+        if two == ptr2 {
+            return if is_num {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            };
+        }
+
+        let mut segment1 = &a[one..ptr1];
+        let mut segment2 = &b[two..ptr2];
+        if is_num {
+            while segment1.first() == Some(&b'0') {
+                segment1 = &segment1[1..];
+            }
+            while segment2.first() == Some(&b'0') {
+                segment2 = &segment2[1..];
+            }
+            // Whichever number has more digits wins
+            match segment1.len().cmp(&segment2.len()) {
+                Ordering::Equal => {}
+                ordering => return ordering,
+            }
+        }
+        match segment1.cmp(segment2) {
+            Ordering::Equal => {}
+            ordering => return ordering,
+        }
+
+        one = ptr1;
+        two = ptr2;
+    }
+
+    let one_done = one >= a.len();
+    let two_done = two >= b.len();
+    if one_done && two_done {
+        return Ordering::Equal;
+    }
+    // A remaining alpha segment never beats an empty string
+    if (one_done && !b[two].is_ascii_alphabetic()) || (!one_done && a[one].is_ascii_alphabetic()) {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // From pacman's test/util/vercmptest.sh
+    const VECTORS: &[(&str, &str, i32)] = &[
+        // all similar length, no pkgrel
+        ("1.5.0", "1.5.0", 0),
+        ("1.5.1", "1.5.0", 1),
+        // mixed length
+        ("1.5.1", "1.5", 1),
+        // with pkgrel, simple
+        ("1.5.0-1", "1.5.0-1", 0),
+        ("1.5.0-1", "1.5.0-2", -1),
+        ("1.5.0-1", "1.5.1-1", -1),
+        ("1.5.0-2", "1.5.1-1", -1),
+        // with pkgrel, mixed lengths
+        ("1.5-1", "1.5.1-1", -1),
+        ("1.5-2", "1.5.1-1", -1),
+        ("1.5-2", "1.5.1-2", -1),
+        // mixed pkgrel inclusion
+        ("1.5", "1.5-1", 0),
+        ("1.5-1", "1.5", 0),
+        ("1.1-1", "1.1", 0),
+        ("1.0-1", "1.1", -1),
+        ("1.1-1", "1.0", 1),
+        // alphanumeric versions
+        ("1.5b-1", "1.5-1", -1),
+        ("1.5b", "1.5", -1),
+        ("1.5b-1", "1.5", -1),
+        ("1.5b", "1.5.1", -1),
+        // from the manpage
+        ("1.0a", "1.0alpha", -1),
+        ("1.0alpha", "1.0b", -1),
+        ("1.0b", "1.0beta", -1),
+        ("1.0beta", "1.0rc", -1),
+        ("1.0rc", "1.0", -1),
+        // going crazy? alpha-dotted versions
+        ("1.5.a", "1.5", 1),
+        ("1.5.b", "1.5.a", 1),
+        ("1.5.1", "1.5.b", 1),
+        // alpha dots and dashes
+        ("1.5.b-1", "1.5.b", 0),
+        ("1.5-1", "1.5.b", -1),
+        // same/similar content, differing separators
+        ("2.0", "2_0", 0),
+        ("2.0_a", "2_0.a", 0),
+        ("2.0a", "2.0.a", -1),
+        ("2___a", "2_a", 1),
+        // epoch included version comparisons
+        ("0:1.0", "0:1.0", 0),
+        ("0:1.0", "0:1.1", -1),
+        ("1:1.0", "0:1.0", 1),
+        ("1:1.0", "0:1.1", 1),
+        ("1:1.0", "2:1.1", -1),
+        // epoch + sometimes present pkgrel
+        ("1:1.0", "0:1.0-1", 1),
+        ("1:1.0-1", "0:1.1-1", 1),
+        // epoch included on one version
+        ("0:1.0", "1.0", 0),
+        ("0:1.0", "1.1", -1),
+        ("0:1.1", "1.0", 1),
+        ("1:1.0", "1.0", 1),
+        ("1:1.0", "1.1", 1),
+        ("1:1.1", "1.1", 1),
+    ];
+
+    #[test]
+    fn matches_pacman_vercmp() {
+        for (a, b, expected) in VECTORS {
+            let expected = expected.cmp(&0);
+            // pacman runs every test both ways round
+            assert_eq!(vercmp(a, b), expected, "vercmp {} {}", a, b);
+            assert_eq!(vercmp(b, a), expected.reverse(), "vercmp {} {}", b, a);
+        }
+    }
+}